@@ -1,107 +1,290 @@
 #![no_std]
 
+pub mod pulse;
+mod temperature;
 #[cfg(test)]
 mod test;
+pub mod tx592;
+pub mod tx5n1;
+pub mod tx609;
+pub mod tx986;
 
-const fn lfsr_sequence<const N: usize>() -> [u8; N] {
-    let mut reg: u8 = 0x7C;
-    let mut sequence = [0u8; N];
+pub use temperature::DeciCelsius;
 
-    let mut i: usize = 0;
-    loop {
-        if i >= N {
-            break;
+/// Checksum used by Acurite devices, computed by XORing together one byte of a
+/// key sequence for each bit that is set in the message. The key sequence is
+/// generated by an 8-bit LFSR: each step rotates the register right by one bit
+/// and, if a one was rotated into the MSB, XORs the taps into the register.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LfsrDigest {
+    seed: u8,
+    taps: u8,
+    length: usize,
+    offset: usize,
+}
+
+impl LfsrDigest {
+    /// Create a digest with the given LFSR seed and taps, that uses a key
+    /// sequence `length` bytes long.
+    pub const fn new(seed: u8, taps: u8, length: usize) -> Self {
+        Self {
+            seed,
+            taps,
+            length,
+            offset: 0,
         }
+    }
+
+    /// Skip the first `offset` bytes of the key sequence.
+    pub const fn offset(mut self, offset: usize) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    const fn step(&self, mut reg: u8) -> u8 {
         reg = reg.rotate_right(1);
         if reg & (1 << 7) != 0 {
-            reg ^= 1 << 3 | 1 << 4;
+            reg ^= self.taps;
         }
-        sequence[i] = reg;
-        i += 1;
+        reg
     }
-    sequence
-}
 
-fn lfsr_hash<const N: usize, const M: usize, const OFFSET: usize>(data: &[u8; N]) -> u8 {
-    let sequence = lfsr_sequence::<M>();
+    /// Generate the first `N` bytes of the key sequence, ignoring the offset.
+    pub const fn sequence<const N: usize>(&self) -> [u8; N] {
+        let mut reg = self.seed;
+        let mut sequence = [0u8; N];
+
+        let mut i: usize = 0;
+        loop {
+            if i >= N {
+                break;
+            }
+            reg = self.step(reg);
+            sequence[i] = reg;
+            i += 1;
+        }
+        sequence
+    }
+
+    /// Compute the digest of a message, MSB first. Panics if the message is
+    /// too long for the key sequence.
+    pub fn digest(&self, data: &[u8]) -> u8 {
+        assert!(self.offset + data.len() * 8 <= self.length);
+
+        let mut reg = self.seed;
+        for _ in 0..self.offset {
+            reg = self.step(reg);
+        }
 
-    let mut hash_reg: u8 = 0;
-    for (byte_idx, byte) in data.iter().enumerate() {
-        let mut byte = *byte;
-        for bit_idx in 0..8 {
-            let bit = byte & 0x80 != 0;
-            if bit {
-                hash_reg ^= sequence[byte_idx * 8 + bit_idx + OFFSET]
+        let mut hash_reg: u8 = 0;
+        for byte in data {
+            let mut byte = *byte;
+            for _ in 0..8 {
+                reg = self.step(reg);
+                if byte & 0x80 != 0 {
+                    hash_reg ^= reg;
+                }
+                byte <<= 1;
             }
-            byte <<= 1;
         }
+
+        hash_reg
     }
+}
+
+/// 8-bit sum of all bytes, used as a checksum by many Acurite devices.
+pub fn sum8(data: &[u8]) -> u8 {
+    data.iter().fold(0, |sum, byte| sum.wrapping_add(*byte))
+}
+
+/// Set the MSB so that the byte has even parity
+pub(crate) const fn with_parity(byte: u8) -> u8 {
+    let byte = byte & 0x7f;
+    byte | ((byte.count_ones() as u8 & 1) << 7)
+}
 
-    hash_reg
+/// CRC-8 with the given polynomial and initial value, MSB first.
+pub const fn crc8(data: &[u8], poly: u8, init: u8) -> u8 {
+    let mut crc = init;
+    let mut i = 0;
+    while i < data.len() {
+        crc ^= data[i];
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 0x80 != 0 {
+                (crc << 1) ^ poly
+            } else {
+                crc << 1
+            };
+            bit += 1;
+        }
+        i += 1;
+    }
+    crc
+}
+
+/// CRC-8 with the given polynomial and initial value, LSB first. This is the
+/// same as [`crc8`] over bytes that are sent LSB first, with the bits of the
+/// bytes and the result reversed.
+pub const fn crc8le(data: &[u8], poly: u8, init: u8) -> u8 {
+    let poly = poly.reverse_bits();
+    let mut crc = init.reverse_bits();
+    let mut i = 0;
+    while i < data.len() {
+        crc ^= data[i];
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 0x01 != 0 {
+                (crc >> 1) ^ poly
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        i += 1;
+    }
+    crc
 }
 
 /// Support for the Acurite 00606TX temperature sensor.
 pub mod tx00606 {
+    use super::{
+        pulse::{self, Encoding, Pulse, Pulses},
+        DeciCelsius, LfsrDigest,
+    };
+
+    /// Digest used to compute the final byte of each message
+    pub const DIGEST: LfsrDigest = LfsrDigest::new(0x7c, 1 << 3 | 1 << 4, 28).offset(4);
+
+    /// Timing of the radio pulses. Each message starts with a long gap, and
+    /// bits are distinguished by the length of the gap following each pulse.
+    pub const ENCODING: Encoding = Encoding {
+        sync: Pulse::new(500, 9000),
+        sync_count: 1,
+        zero: Pulse::new(500, 2000),
+        one: Pulse::new(500, 4000),
+        stop: Some(Pulse::new(500, 500)),
+    };
+
+    /// Number of times each message is sent
+    pub const REPEATS: usize = 7;
+
+    /// Lowest temperature that fits in the 12-bit temperature field
+    pub const TEMPERATURE_MIN: DeciCelsius = DeciCelsius::new(-2048);
+    /// Highest temperature that fits in the 12-bit temperature field
+    pub const TEMPERATURE_MAX: DeciCelsius = DeciCelsius::new(2047);
+
+    const STATUS_BATTERY_OK: u8 = 0b1000;
+    const STATUS_RESERVED: u8 = 0b0111;
+
+    /// Reasons a received message could not be decoded.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum Error {
+        /// The temperature cannot be represented in a message.
+        TemperatureRange(DeciCelsius),
+        /// The hash byte does not match the hash computed from the rest of the
+        /// message.
+        Checksum { expected: u8, actual: u8 },
+        /// One of the status bits that are always zero in genuine messages was
+        /// set.
+        ReservedBits(u8),
+    }
+
+    /// A single 00606TX message.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Message {
+        id: u8,
+        battery_ok: bool,
+        temperature: DeciCelsius,
+    }
 
-    /// Convert a left justified, 9.4 fixed point temperature to the decimal format used by the 0606TX. This is the format used by sensors such as the TMP102.
-    pub const fn convert_temperature(temp_reg: i16) -> i16 {
-        let temp_whole = temp_reg >> 7;
-        // Binary fractional part, out of 16
-        let temp_frac_bin = ((temp_reg & 0x7f) >> 3) as u8;
-        // Convert to decimal fraction, with proper rounding
-        let temp_frac = if temp_whole >= 0 {
-            match temp_frac_bin {
-                0 => 0,  // 0
-                1 => 1,  // 0.0625
-                2 => 1,  // 0.125
-                3 => 2,  // 0.1875
-                4 => 3,  // 0.25
-                5 => 3,  // 0.3125
-                6 => 4,  // 0.375
-                7 => 4,  // 0.4375
-                8 => 5,  // 0.5
-                9 => 6,  // 0.5625
-                10 => 6, // 0.625
-                11 => 7, // 0.6875
-                12 => 8, // 0.75
-                13 => 8, // 0.8125
-                14 => 9, // 0.875
-                15 => 9, // 0.9375
-                _ => unreachable!(),
+    impl Message {
+        /// Create a message, failing if the temperature is outside of the range
+        /// -204.8 to 204.7 °C.
+        pub const fn new(
+            id: u8,
+            battery_ok: bool,
+            temperature: DeciCelsius,
+        ) -> Result<Self, Error> {
+            if temperature.tenths() < TEMPERATURE_MIN.tenths()
+                || temperature.tenths() > TEMPERATURE_MAX.tenths()
+            {
+                return Err(Error::TemperatureRange(temperature));
             }
-        } else {
-            match temp_frac_bin {
-                0 => 0,  // 0
-                1 => 1,  // 0.0625
-                2 => 1,  // 0.125
-                3 => 2,  // 0.1875
-                4 => 2,  // 0.25
-                5 => 3,  // 0.3125
-                6 => 4,  // 0.375
-                7 => 4,  // 0.4375
-                8 => 5,  // 0.5
-                9 => 6,  // 0.5625
-                10 => 6, // 0.625
-                11 => 7, // 0.6875
-                12 => 7, // 0.75
-                13 => 8, // 0.8125
-                14 => 9, // 0.875
-                15 => 9, // 0.9375
-                _ => unreachable!(),
+            Ok(Self {
+                id,
+                battery_ok,
+                temperature,
+            })
+        }
+
+        pub const fn id(&self) -> u8 {
+            self.id
+        }
+
+        pub const fn battery_ok(&self) -> bool {
+            self.battery_ok
+        }
+
+        pub const fn temperature(&self) -> DeciCelsius {
+            self.temperature
+        }
+
+        pub fn to_bytes(&self) -> [u8; 4] {
+            let status = if self.battery_ok {
+                STATUS_BATTERY_OK
+            } else {
+                0b0000
+            };
+            let temperature = self.temperature.tenths();
+            let body: [u8; 3] = [
+                self.id,
+                status << 4 | ((temperature >> 8) as u8 & 0x0f),
+                (temperature & 0xff) as u8,
+            ];
+            let hash = DIGEST.digest(&body);
+            [body[0], body[1], body[2], hash]
+        }
+
+        /// Radio pulses to transmit this message
+        pub fn pulses(&self) -> Pulses<4> {
+            ENCODING.encode(self.to_bytes())
+        }
+
+        /// Decode a received message, verifying its hash.
+        pub fn from_bytes(bytes: [u8; 4]) -> Result<Self, Error> {
+            let [id, status_temp, temp_low, hash] = bytes;
+
+            let expected = DIGEST.digest(&[id, status_temp, temp_low]);
+            if hash != expected {
+                return Err(Error::Checksum {
+                    expected,
+                    actual: hash,
+                });
+            }
+
+            let status = status_temp >> 4;
+            if status & STATUS_RESERVED != 0 {
+                return Err(Error::ReservedBits(status & STATUS_RESERVED));
             }
-        };
 
-        temp_whole * 10 + temp_frac as i16
+            Ok(Self {
+                id,
+                battery_ok: status & STATUS_BATTERY_OK != 0,
+                // Sign extend the 12-bit temperature
+                temperature: DeciCelsius::new(
+                    (i16::from_be_bytes([status_temp & 0x0f, temp_low]) << 4) >> 4,
+                ),
+            })
+        }
     }
 
-    pub fn generate(id: u8, battery_ok: bool, temperature: i16) -> [u8; 4] {
-        let status = if battery_ok { 0b1000 } else { 0b0000 };
-        let body: [u8; 3] = [
-            id,
-            status << 4 | ((temperature >> 8) as u8 & 0x0f),
-            (temperature & 0xff) as u8,
-        ];
-        let hash = super::lfsr_hash::<3, 28, 4>(&body);
-        [body[0], body[1], body[2], hash]
+    /// Receives 00606TX messages from radio pulses
+    pub type Receiver = pulse::Receiver<fn([u8; 4]) -> Result<Message, Error>, 4>;
+
+    /// Create a receiver that accepts pulses within `tolerance_pct` percent
+    /// of the nominal timing.
+    pub const fn receiver(tolerance_pct: u8) -> Receiver {
+        Receiver::new(ENCODING, tolerance_pct, Message::from_bytes)
     }
 }
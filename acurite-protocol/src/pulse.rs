@@ -0,0 +1,178 @@
+//! Conversion of messages to the on-off keyed pulses sent by the radio.
+
+/// The transmitter is switched on for `mark_us` microseconds, then off for
+/// `space_us` microseconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pulse {
+    pub mark_us: u16,
+    pub space_us: u16,
+}
+
+impl Pulse {
+    pub const fn new(mark_us: u16, space_us: u16) -> Self {
+        Self { mark_us, space_us }
+    }
+}
+
+/// Description of the pulses that make up a message: `sync_count` sync pulses,
+/// one pulse per data bit (MSB first) and an optional stop pulse.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Encoding {
+    pub sync: Pulse,
+    pub sync_count: u8,
+    pub zero: Pulse,
+    pub one: Pulse,
+    pub stop: Option<Pulse>,
+}
+
+impl Encoding {
+    pub const fn encode<const N: usize>(&self, data: [u8; N]) -> Pulses<N> {
+        Pulses {
+            encoding: *self,
+            data,
+            index: 0,
+        }
+    }
+
+    const fn len(&self, bits: usize) -> usize {
+        self.sync_count as usize + bits + if self.stop.is_some() { 1 } else { 0 }
+    }
+}
+
+/// Iterator over the pulses of an encoded message
+#[derive(Debug, Clone)]
+pub struct Pulses<const N: usize> {
+    encoding: Encoding,
+    data: [u8; N],
+    index: usize,
+}
+
+impl<const N: usize> Iterator for Pulses<N> {
+    type Item = Pulse;
+
+    fn next(&mut self) -> Option<Pulse> {
+        let sync_count = self.encoding.sync_count as usize;
+        let pulse = if self.index < sync_count {
+            self.encoding.sync
+        } else if self.index < sync_count + N * 8 {
+            let bit = self.index - sync_count;
+            if self.data[bit / 8] & (0x80 >> (bit % 8)) != 0 {
+                self.encoding.one
+            } else {
+                self.encoding.zero
+            }
+        } else if self.index == sync_count + N * 8 {
+            self.encoding.stop?
+        } else {
+            return None;
+        };
+        self.index += 1;
+        Some(pulse)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.encoding.len(N * 8).saturating_sub(self.index);
+        (remaining, Some(remaining))
+    }
+}
+
+impl<const N: usize> ExactSizeIterator for Pulses<N> {}
+
+/// Recovers messages from received pulses by matching them against an
+/// [`Encoding`]. A pulse matches if both its mark and space are within
+/// `tolerance_pct` percent of the nominal durations. Durations that do not fit
+/// in a `u16`, such as the idle time between messages, should be saturated.
+#[derive(Debug, Clone)]
+pub struct Decoder<const N: usize> {
+    encoding: Encoding,
+    tolerance_pct: u8,
+    data: [u8; N],
+    /// Number of bits received since the last sync pulse, or `None` if not
+    /// synchronized
+    bits: Option<usize>,
+}
+
+impl<const N: usize> Decoder<N> {
+    pub const fn new(encoding: Encoding, tolerance_pct: u8) -> Self {
+        Self {
+            encoding,
+            tolerance_pct,
+            data: [0u8; N],
+            bits: None,
+        }
+    }
+
+    fn duration_matches(&self, actual: u16, nominal: u16) -> bool {
+        let error = (actual as u32).abs_diff(nominal as u32);
+        error * 100 <= nominal as u32 * self.tolerance_pct as u32
+    }
+
+    fn matches(&self, pulse: Pulse, nominal: Pulse) -> bool {
+        self.duration_matches(pulse.mark_us, nominal.mark_us)
+            && self.duration_matches(pulse.space_us, nominal.space_us)
+    }
+
+    /// Discard any partially received message.
+    pub fn reset(&mut self) {
+        self.bits = None;
+    }
+
+    /// Process the next received pulse, returning the message if it was the
+    /// last bit.
+    pub fn feed(&mut self, pulse: Pulse) -> Option<[u8; N]> {
+        if self.matches(pulse, self.encoding.sync) {
+            self.data = [0u8; N];
+            self.bits = Some(0);
+            return None;
+        }
+
+        let bits = self.bits?;
+        if self.matches(pulse, self.encoding.one) {
+            self.data[bits / 8] |= 0x80 >> (bits % 8);
+        } else if !self.matches(pulse, self.encoding.zero) {
+            self.reset();
+            return None;
+        }
+
+        if bits + 1 == N * 8 {
+            self.reset();
+            Some(self.data)
+        } else {
+            self.bits = Some(bits + 1);
+            None
+        }
+    }
+}
+
+/// Receives messages from radio pulses, such as those captured by a logic
+/// analyzer or SDR. The bytes of each message are passed to `decode`.
+#[derive(Debug, Clone)]
+pub struct Receiver<F, const N: usize> {
+    decoder: Decoder<N>,
+    decode: F,
+}
+
+impl<F, const N: usize> Receiver<F, N> {
+    /// Create a receiver that accepts pulses within `tolerance_pct` percent of
+    /// the nominal timing.
+    pub const fn new(encoding: Encoding, tolerance_pct: u8, decode: F) -> Self {
+        Self {
+            decoder: Decoder::new(encoding, tolerance_pct),
+            decode,
+        }
+    }
+
+    /// Discard any partially received message.
+    pub fn reset(&mut self) {
+        self.decoder.reset();
+    }
+
+    /// Process the next received pulse, returning the decoded message once all
+    /// of its bits have been received.
+    pub fn feed<M, E>(&mut self, pulse: Pulse) -> Option<Result<M, E>>
+    where
+        F: FnMut([u8; N]) -> Result<M, E>,
+    {
+        self.decoder.feed(pulse).map(&mut self.decode)
+    }
+}
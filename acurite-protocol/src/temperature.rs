@@ -0,0 +1,153 @@
+//! Fixed point temperature type shared by all protocols.
+
+use core::fmt;
+
+/// Temperature in tenths of a degree Celsius, which is the resolution used by
+/// most Acurite sensors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct DeciCelsius(i16);
+
+/// Divide by a positive divisor, rounding halves away from zero
+const fn div_round(n: i32, d: i32) -> i32 {
+    // Widen so that adding half the divisor can't overflow
+    let (n, d) = (n as i64, d as i64);
+    let half = d / 2;
+    let q = if n < 0 {
+        (n - half) / d
+    } else {
+        (n + half) / d
+    };
+    // The magnitude doesn't grow, so it still fits
+    q as i32
+}
+
+impl DeciCelsius {
+    pub const ZERO: Self = Self(0);
+    pub const MIN: Self = Self(i16::MIN);
+    pub const MAX: Self = Self(i16::MAX);
+
+    pub const fn new(tenths: i16) -> Self {
+        Self(tenths)
+    }
+
+    /// Temperature in tenths of a degree Celsius
+    pub const fn tenths(self) -> i16 {
+        self.0
+    }
+
+    pub const fn checked_add(self, rhs: Self) -> Option<Self> {
+        match self.0.checked_add(rhs.0) {
+            Some(t) => Some(Self(t)),
+            None => None,
+        }
+    }
+
+    pub const fn checked_sub(self, rhs: Self) -> Option<Self> {
+        match self.0.checked_sub(rhs.0) {
+            Some(t) => Some(Self(t)),
+            None => None,
+        }
+    }
+
+    pub const fn checked_neg(self) -> Option<Self> {
+        match self.0.checked_neg() {
+            Some(t) => Some(Self(t)),
+            None => None,
+        }
+    }
+
+    /// Convert from sixteenths of a degree, which is the resolution of sensors
+    /// such as the TMP102.
+    const fn from_sixteenths(sixteenths: i16) -> Self {
+        Self(div_round(sixteenths as i32 * 10, 16) as i16)
+    }
+
+    const fn to_sixteenths(self) -> i16 {
+        div_round(self.0 as i32 * 16, 10) as i16
+    }
+
+    /// Convert a left justified, 8.4 fixed point temperature register, as
+    /// used by the TMP102 in normal (12-bit) mode.
+    pub const fn from_tmp102(temp_reg: i16) -> Self {
+        Self::from_sixteenths(temp_reg >> 4)
+    }
+
+    /// Convert a left justified, 9.4 fixed point temperature register, as
+    /// used by the TMP102 in extended (13-bit) mode.
+    pub const fn from_tmp102_extended(temp_reg: i16) -> Self {
+        Self::from_sixteenths(temp_reg >> 3)
+    }
+
+    /// Convert to a TMP102 normal (12-bit) mode register value, or `None` if
+    /// the temperature is outside of the -128 to 127.9375 °C range.
+    pub const fn to_tmp102(self) -> Option<i16> {
+        let sixteenths = self.to_sixteenths();
+        if sixteenths < -0x800 || sixteenths > 0x7ff {
+            return None;
+        }
+        Some(sixteenths << 4)
+    }
+
+    /// Convert to a TMP102 extended (13-bit) mode register value, including
+    /// the extended mode flag bit, or `None` if the temperature is outside of
+    /// the -256 to 255.9375 °C range.
+    pub const fn to_tmp102_extended(self) -> Option<i16> {
+        let sixteenths = self.to_sixteenths();
+        if sixteenths < -0x1000 || sixteenths > 0xfff {
+            return None;
+        }
+        Some(sixteenths << 3 | 1)
+    }
+
+    pub const fn from_millidegrees(millidegrees: i32) -> Option<Self> {
+        let tenths = div_round(millidegrees, 100);
+        if tenths < i16::MIN as i32 || tenths > i16::MAX as i32 {
+            return None;
+        }
+        Some(Self(tenths as i16))
+    }
+
+    pub const fn to_millidegrees(self) -> i32 {
+        self.0 as i32 * 100
+    }
+
+    /// Convert from tenths of a degree Fahrenheit
+    pub const fn from_deci_fahrenheit(tenths: i16) -> Self {
+        Self(div_round((tenths as i32 - 320) * 5, 9) as i16)
+    }
+
+    /// Convert to tenths of a degree Fahrenheit, or `None` if the result does
+    /// not fit.
+    pub const fn to_deci_fahrenheit(self) -> Option<i16> {
+        let tenths = div_round(self.0 as i32 * 9, 5) + 320;
+        if tenths < i16::MIN as i32 || tenths > i16::MAX as i32 {
+            return None;
+        }
+        Some(tenths as i16)
+    }
+
+    /// Convert to the nearest whole degree Fahrenheit
+    pub const fn to_fahrenheit(self) -> i16 {
+        div_round(self.0 as i32 * 9 + 1600, 50) as i16
+    }
+}
+
+impl fmt::Display for DeciCelsius {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let abs = self.0.unsigned_abs();
+        write!(f, "{}{}.{}", sign, abs / 10, abs % 10)
+    }
+}
+
+#[cfg(feature = "ufmt")]
+impl ufmt::uDisplay for DeciCelsius {
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: ufmt::uWrite + ?Sized,
+    {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let abs = self.0.unsigned_abs();
+        ufmt::uwrite!(f, "{}{}.{}", sign, abs / 10, abs % 10)
+    }
+}
@@ -1,8 +1,59 @@
+extern crate std;
+
 use super::*;
 
+/// Simple deterministic PRNG for adding jitter to pulse timings
+struct Lcg(u32);
+
+impl Lcg {
+    fn next(&mut self) -> u32 {
+        self.0 = self.0.wrapping_mul(1664525).wrapping_add(1013904223);
+        self.0 >> 16
+    }
+
+    /// Randomly adjust a duration by up to `pct` percent
+    fn jitter(&mut self, duration: u16, pct: u32) -> u16 {
+        let max = duration as u32 * pct / 100;
+        let offset = self.next() % (2 * max + 1);
+        (duration as u32 + offset - max) as u16
+    }
+}
+
+/// Send each message `repeats` times, with up to `pct` percent of jitter on
+/// every pulse, and check that the receiver decodes every copy
+fn assert_receives_with_jitter<F, M, E, const N: usize>(
+    rng: &mut Lcg,
+    receiver: &mut pulse::Receiver<F, N>,
+    messages: &[M],
+    pulses: fn(&M) -> pulse::Pulses<N>,
+    repeats: usize,
+    pct: u32,
+) where
+    F: FnMut([u8; N]) -> Result<M, E>,
+    M: core::fmt::Debug + PartialEq,
+    E: core::fmt::Debug + PartialEq,
+{
+    let mut received = 0;
+    for message in messages {
+        for _ in 0..repeats {
+            for pulse in pulses(message) {
+                let pulse = pulse::Pulse::new(
+                    rng.jitter(pulse.mark_us, pct),
+                    rng.jitter(pulse.space_us, pct),
+                );
+                if let Some(result) = receiver.feed(pulse) {
+                    assert_eq!(Ok(message), result.as_ref());
+                    received += 1;
+                }
+            }
+        }
+    }
+    assert_eq!(messages.len() * repeats, received);
+}
+
 #[test]
 fn test_lfsr_sequence() {
-    let seq = lfsr_sequence::<28>();
+    let seq = tx00606::DIGEST.sequence::<28>();
     assert_eq!(
         [
             0x3e, 0x1f, 0x97, 0xd3, 0xf1, 0xe0, 0x70, 0x38, 0x1c, 0x0e, 0x07, 0x9b, 0xd5, 0xf2,
@@ -14,38 +65,1030 @@ fn test_lfsr_sequence() {
 
 #[test]
 fn test_lfsr_hash_zero() {
-    let hash = lfsr_hash::<3, 28, 4>(&[0x00, 0x00, 0x00]);
+    let hash = tx00606::DIGEST.digest(&[0x00, 0x00, 0x00]);
     assert_eq!(0x00, hash);
 }
 
 #[test]
 fn test_lfsr_hash() {
-    let hash = lfsr_hash::<3, 28, 4>(&[0xe6, 0x81, 0x36]);
+    let hash = tx00606::DIGEST.digest(&[0xe6, 0x81, 0x36]);
     assert_eq!(0x13, hash);
 }
 
 #[test]
-fn test_0606tx_convert_temperature() {
-    assert_eq!(1500, tx00606::convert_temperature(0x0960 << 3)); // 150
-    assert_eq!(1280, tx00606::convert_temperature(0x0800 << 3)); // 128
-    assert_eq!(1279, tx00606::convert_temperature(0x07ff << 3)); // 127.9375
-    assert_eq!(3, tx00606::convert_temperature(0x0004 << 3)); // 0.25
-    assert_eq!(-3, tx00606::convert_temperature(0x1ffc << 3)); // -0.25
-    assert_eq!(-8, tx00606::convert_temperature(0x1ff4 << 3)); // -0.75
-    assert_eq!(-254, tx00606::convert_temperature(0x1e6a << 3)); // -25.375
-    assert_eq!(-455, tx00606::convert_temperature(0x1d28 << 3)); // -45.5
-    assert_eq!(-456, tx00606::convert_temperature(0x1d26 << 3)); // -45.625
-    assert_eq!(-458, tx00606::convert_temperature(0x1d24 << 3)); // -45.75
+fn test_lfsr_digest_single_bits() {
+    // Each set bit contributes exactly one byte of the key sequence
+    let seq = tx00606::DIGEST.sequence::<28>();
+    for bit in 0..24 {
+        let data = (0x80_0000u32 >> bit).to_be_bytes();
+        assert_eq!(seq[bit + 4], tx00606::DIGEST.digest(&data[1..]));
+    }
+}
+
+#[test]
+fn test_lfsr_digest_offset() {
+    // Skipping part of the key sequence is the same as prefixing the message
+    // with zero bits
+    let digest = LfsrDigest::new(0x7c, 1 << 3 | 1 << 4, 32);
+    assert_eq!(0x13, digest.offset(4).digest(&[0xe6, 0x81, 0x36]));
+    assert_eq!(0x13, digest.digest(&[0x0e, 0x68, 0x13, 0x60]));
+}
+
+#[test]
+#[should_panic]
+fn test_lfsr_digest_too_long() {
+    tx00606::DIGEST.digest(&[0x00, 0x00, 0x00, 0x00]);
+}
+
+#[test]
+fn test_deci_celsius_from_tmp102_extended() {
+    assert_eq!(
+        DeciCelsius::new(1500),
+        DeciCelsius::from_tmp102_extended(0x0960 << 3)
+    ); // 150
+    assert_eq!(
+        DeciCelsius::new(1280),
+        DeciCelsius::from_tmp102_extended(0x0800 << 3)
+    ); // 128
+    assert_eq!(
+        DeciCelsius::new(1279),
+        DeciCelsius::from_tmp102_extended(0x07ff << 3)
+    ); // 127.9375
+    assert_eq!(
+        DeciCelsius::new(3),
+        DeciCelsius::from_tmp102_extended(0x0004 << 3)
+    ); // 0.25
+    assert_eq!(
+        DeciCelsius::new(-3),
+        DeciCelsius::from_tmp102_extended(0x1ffc << 3)
+    ); // -0.25
+    assert_eq!(
+        DeciCelsius::new(-8),
+        DeciCelsius::from_tmp102_extended(0x1ff4 << 3)
+    ); // -0.75
+    assert_eq!(
+        DeciCelsius::new(-254),
+        DeciCelsius::from_tmp102_extended(0x1e6a << 3)
+    ); // -25.375
+    assert_eq!(
+        DeciCelsius::new(-455),
+        DeciCelsius::from_tmp102_extended(0x1d28 << 3)
+    ); // -45.5
+    assert_eq!(
+        DeciCelsius::new(-456),
+        DeciCelsius::from_tmp102_extended(0x1d26 << 3)
+    ); // -45.625
+    assert_eq!(
+        DeciCelsius::new(-458),
+        DeciCelsius::from_tmp102_extended(0x1d24 << 3)
+    ); // -45.75
+}
+
+#[test]
+fn test_deci_celsius_from_tmp102() {
+    let d = DeciCelsius::new;
+    assert_eq!(d(1279), DeciCelsius::from_tmp102(0x7ff0)); // 127.9375
+    assert_eq!(d(3), DeciCelsius::from_tmp102(0x0040)); // 0.25
+    assert_eq!(d(0), DeciCelsius::from_tmp102(0x0000));
+    assert_eq!(d(-3), DeciCelsius::from_tmp102(0xffc0u16 as i16)); // -0.25
+    assert_eq!(d(-254), DeciCelsius::from_tmp102(0xe6a0u16 as i16)); // -25.375
+    assert_eq!(d(-550), DeciCelsius::from_tmp102(0xc900u16 as i16)); // -55
+}
+
+#[test]
+fn test_deci_celsius_to_tmp102() {
+    let d = DeciCelsius::new;
+    assert_eq!(Some(0x7fe0), d(1279).to_tmp102()); // 127.875
+    assert_eq!(Some(0x0050), d(3).to_tmp102()); // 0.3125
+    assert_eq!(Some(0xe6a0u16 as i16), d(-254).to_tmp102());
+    assert_eq!(None, d(1280).to_tmp102());
+    assert_eq!(Some(0x8000u16 as i16), d(-1280).to_tmp102());
+    assert_eq!(None, d(-1281).to_tmp102());
+
+    assert_eq!(Some(0x4b01), d(1500).to_tmp102_extended());
+    assert_eq!(Some(0xf351u16 as i16), d(-254).to_tmp102_extended());
+    assert_eq!(None, d(2560).to_tmp102_extended());
+
+    for tenths in -1280..1280 {
+        let temp = d(tenths);
+        assert_eq!(temp, DeciCelsius::from_tmp102(temp.to_tmp102().unwrap()));
+        assert_eq!(
+            temp,
+            DeciCelsius::from_tmp102_extended(temp.to_tmp102_extended().unwrap())
+        );
+    }
+}
+
+#[test]
+fn test_deci_celsius_checked_arithmetic() {
+    let d = DeciCelsius::new;
+    assert_eq!(Some(d(55)), d(30).checked_add(d(25)));
+    assert_eq!(Some(d(5)), d(30).checked_sub(d(25)));
+    assert_eq!(Some(d(-30)), d(30).checked_neg());
+    assert_eq!(None, DeciCelsius::MAX.checked_add(d(1)));
+    assert_eq!(None, DeciCelsius::MIN.checked_sub(d(1)));
+    assert_eq!(None, DeciCelsius::MIN.checked_neg());
+}
+
+#[test]
+fn test_deci_celsius_fahrenheit() {
+    let d = DeciCelsius::new;
+    assert_eq!(Some(320), d(0).to_deci_fahrenheit());
+    assert_eq!(Some(2120), d(1000).to_deci_fahrenheit());
+    assert_eq!(Some(-400), d(-400).to_deci_fahrenheit());
+    assert_eq!(Some(-137), d(-254).to_deci_fahrenheit()); // -13.72
+    assert_eq!(None, DeciCelsius::MAX.to_deci_fahrenheit());
+
+    assert_eq!(d(0), DeciCelsius::from_deci_fahrenheit(320));
+    assert_eq!(d(1000), DeciCelsius::from_deci_fahrenheit(2120));
+    assert_eq!(d(-254), DeciCelsius::from_deci_fahrenheit(-137)); // -25.39
+}
+
+#[test]
+fn test_deci_celsius_millidegrees() {
+    let d = DeciCelsius::new;
+    assert_eq!(-25400, d(-254).to_millidegrees());
+    assert_eq!(Some(d(-254)), DeciCelsius::from_millidegrees(-25375));
+    assert_eq!(Some(d(-253)), DeciCelsius::from_millidegrees(-25349));
+    assert_eq!(Some(d(3)), DeciCelsius::from_millidegrees(250));
+    assert_eq!(None, DeciCelsius::from_millidegrees(3_276_800));
+    assert_eq!(Some(d(i16::MAX)), DeciCelsius::from_millidegrees(3_276_749));
+    assert_eq!(Some(d(i16::MIN)), DeciCelsius::from_millidegrees(-3_276_849));
+    assert_eq!(None, DeciCelsius::from_millidegrees(-3_276_850));
+    assert_eq!(None, DeciCelsius::from_millidegrees(i32::MIN));
+    assert_eq!(None, DeciCelsius::from_millidegrees(i32::MAX));
+}
+
+#[test]
+fn test_deci_celsius_display() {
+    let d = DeciCelsius::new;
+    assert_eq!("-25.4", std::format!("{}", d(-254)));
+    assert_eq!("-0.5", std::format!("{}", d(-5)));
+    assert_eq!("0.0", std::format!("{}", d(0)));
+    assert_eq!("31.0", std::format!("{}", d(310)));
+    assert_eq!("-3276.8", std::format!("{}", DeciCelsius::MIN));
 }
 
 #[test]
 fn test_0606tx_message() {
-    let message = tx00606::generate(0xe6, true, 310);
+    let message = tx00606::Message::new(0xe6, true, DeciCelsius::new(310))
+        .unwrap()
+        .to_bytes();
     assert_eq!([0xe6, 0x81, 0x36, 0x13], message);
 }
 
 #[test]
 fn test_0606tx_message_low_battery() {
-    let message = tx00606::generate(0xe6, false, 310);
+    let message = tx00606::Message::new(0xe6, false, DeciCelsius::new(310))
+        .unwrap()
+        .to_bytes();
     assert_eq!([0xe6, 0x01, 0x36, 0xC6], message);
 }
+
+#[test]
+fn test_0606tx_message_temperature_range() {
+    assert!(tx00606::Message::new(0xe6, true, tx00606::TEMPERATURE_MIN).is_ok());
+    assert!(tx00606::Message::new(0xe6, true, tx00606::TEMPERATURE_MAX).is_ok());
+    assert_eq!(
+        Err(tx00606::Error::TemperatureRange(DeciCelsius::new(-2049))),
+        tx00606::Message::new(0xe6, true, DeciCelsius::new(-2049))
+    );
+    assert_eq!(
+        Err(tx00606::Error::TemperatureRange(DeciCelsius::new(2048))),
+        tx00606::Message::new(0xe6, true, DeciCelsius::new(2048))
+    );
+}
+
+#[test]
+fn test_0606tx_from_bytes() {
+    let message = tx00606::Message::from_bytes([0xe6, 0x81, 0x36, 0x13]).unwrap();
+    assert_eq!(0xe6, message.id());
+    assert!(message.battery_ok());
+    assert_eq!(DeciCelsius::new(310), message.temperature());
+
+    let message = tx00606::Message::from_bytes([0xe6, 0x01, 0x36, 0xC6]).unwrap();
+    assert_eq!(0xe6, message.id());
+    assert!(!message.battery_ok());
+    assert_eq!(DeciCelsius::new(310), message.temperature());
+}
+
+#[test]
+fn test_0606tx_round_trip() {
+    for id in [0x00, 0x01, 0x5a, 0xe6, 0xff] {
+        for battery_ok in [false, true] {
+            for temperature in [-2048, -458, -254, -8, -3, 0, 3, 310, 1279, 1500, 2047] {
+                let message =
+                    tx00606::Message::new(id, battery_ok, DeciCelsius::new(temperature)).unwrap();
+                assert_eq!(
+                    Ok(message),
+                    tx00606::Message::from_bytes(message.to_bytes())
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn test_0606tx_from_bytes_bad_checksum() {
+    assert_eq!(
+        Err(tx00606::Error::Checksum {
+            expected: 0x13,
+            actual: 0x12
+        }),
+        tx00606::Message::from_bytes([0xe6, 0x81, 0x36, 0x12])
+    );
+}
+
+#[test]
+fn test_0606tx_from_bytes_reserved_bits() {
+    let body = [0xe6, 0xc1, 0x36];
+    let hash = tx00606::DIGEST.digest(&body);
+    assert_eq!(
+        Err(tx00606::Error::ReservedBits(0b0100)),
+        tx00606::Message::from_bytes([body[0], body[1], body[2], hash])
+    );
+}
+
+#[test]
+fn test_pulse_encoding() {
+    let encoding = pulse::Encoding {
+        sync: pulse::Pulse::new(600, 600),
+        sync_count: 2,
+        zero: pulse::Pulse::new(400, 200),
+        one: pulse::Pulse::new(200, 400),
+        stop: None,
+    };
+    let pulses = encoding.encode([0b1010_0000]);
+    assert_eq!(10, pulses.len());
+    assert!(pulses.eq([
+        pulse::Pulse::new(600, 600),
+        pulse::Pulse::new(600, 600),
+        pulse::Pulse::new(200, 400),
+        pulse::Pulse::new(400, 200),
+        pulse::Pulse::new(200, 400),
+        pulse::Pulse::new(400, 200),
+        pulse::Pulse::new(400, 200),
+        pulse::Pulse::new(400, 200),
+        pulse::Pulse::new(400, 200),
+        pulse::Pulse::new(400, 200),
+    ]));
+}
+
+#[test]
+fn test_0606tx_pulses() {
+    let message = tx00606::Message::new(0xe6, true, DeciCelsius::new(310)).unwrap();
+    let mut pulses = message.pulses();
+    assert_eq!(34, pulses.len());
+
+    assert_eq!(Some(pulse::Pulse::new(500, 9000)), pulses.next());
+    // 0xe6, 0x81, 0x36, 0x13
+    let bits = 0xe6813613u32;
+    for i in 0..32 {
+        let space_us = if bits & (1 << (31 - i)) != 0 {
+            4000
+        } else {
+            2000
+        };
+        assert_eq!(Some(pulse::Pulse::new(500, space_us)), pulses.next());
+    }
+    assert_eq!(Some(pulse::Pulse::new(500, 500)), pulses.next());
+    assert_eq!(None, pulses.next());
+    assert_eq!(0, pulses.len());
+}
+
+#[test]
+fn test_pulse_decoder_resync() {
+    let mut decoder = pulse::Decoder::<1>::new(tx00606::ENCODING, 20);
+    // Bits before the first sync pulse are ignored
+    assert_eq!(None, decoder.feed(pulse::Pulse::new(500, 4000)));
+    assert_eq!(None, decoder.feed(pulse::Pulse::new(500, 9000)));
+    for _ in 0..4 {
+        assert_eq!(None, decoder.feed(pulse::Pulse::new(500, 4000)));
+    }
+    // Unrecognized pulse discards the partial message
+    assert_eq!(None, decoder.feed(pulse::Pulse::new(500, 3000)));
+    for _ in 0..8 {
+        assert_eq!(None, decoder.feed(pulse::Pulse::new(500, 2000)));
+    }
+    assert_eq!(None, decoder.feed(pulse::Pulse::new(500, 9000)));
+    for _ in 0..7 {
+        assert_eq!(None, decoder.feed(pulse::Pulse::new(500, 4000)));
+    }
+    assert_eq!(Some([0xff]), decoder.feed(pulse::Pulse::new(500, 4000)));
+}
+
+#[test]
+fn test_0606tx_receiver_jitter() {
+    let mut rng = Lcg(1);
+    let mut receiver = tx00606::receiver(25);
+
+    // Noise before the first message
+    for _ in 0..20 {
+        let pulse = pulse::Pulse::new(rng.next() as u16 % 5000, rng.next() as u16 % 5000);
+        if let Some(Ok(_)) = receiver.feed(pulse) {
+            panic!("decoded message from noise");
+        }
+    }
+
+    let messages = [-2048, -254, 0, 310, 2047].map(|temperature| {
+        tx00606::Message::new(0x5a, temperature > 0, DeciCelsius::new(temperature)).unwrap()
+    });
+    assert_receives_with_jitter(
+        &mut rng,
+        &mut receiver,
+        &messages,
+        tx00606::Message::pulses,
+        7,
+        15,
+    );
+}
+
+#[test]
+fn test_0606tx_receiver_bad_checksum() {
+    let mut receiver = tx00606::receiver(25);
+    let mut result = None;
+    for pulse in tx00606::ENCODING.encode([0xe6, 0x81, 0x36, 0x12]) {
+        result = result.or(receiver.feed(pulse));
+    }
+    assert_eq!(
+        Some(Err(tx00606::Error::Checksum {
+            expected: 0x13,
+            actual: 0x12
+        })),
+        result
+    );
+}
+
+#[test]
+fn test_592txr_message() {
+    let message =
+        tx592::Message::new(tx592::Channel::A, 1, 0x123, true, 45, DeciCelsius::new(231)).unwrap();
+    assert_eq!(
+        [0xd1, 0x23, 0x44, 0x2d, 0x09, 0xcf, 0x3d],
+        message.to_bytes()
+    );
+}
+
+#[test]
+fn test_592txr_message_low_battery() {
+    let message = tx592::Message::new(
+        tx592::Channel::C,
+        0,
+        0x123,
+        false,
+        45,
+        DeciCelsius::new(-231),
+    )
+    .unwrap();
+    // Temperature: 769 = 0x301
+    assert_eq!(
+        [0x01, 0x23, 0x84, 0x2d, 0x06, 0x81, 0x5c],
+        message.to_bytes()
+    );
+}
+
+#[test]
+fn test_592txr_message_range() {
+    use tx592::{Channel, Error, Message};
+    assert_eq!(
+        Err(Error::SequenceRange(4)),
+        Message::new(Channel::A, 4, 0, true, 0, DeciCelsius::ZERO)
+    );
+    assert_eq!(
+        Err(Error::IdRange(0x1000)),
+        Message::new(Channel::A, 0, 0x1000, true, 0, DeciCelsius::ZERO)
+    );
+    assert_eq!(
+        Err(Error::HumidityRange(101)),
+        Message::new(Channel::A, 0, 0, true, 101, DeciCelsius::ZERO)
+    );
+    assert_eq!(
+        Err(Error::TemperatureRange(DeciCelsius::new(-1001))),
+        Message::new(Channel::A, 0, 0, true, 0, DeciCelsius::new(-1001))
+    );
+    assert_eq!(
+        Err(Error::TemperatureRange(DeciCelsius::new(1048))),
+        Message::new(Channel::A, 0, 0, true, 0, DeciCelsius::new(1048))
+    );
+}
+
+#[test]
+fn test_592txr_temperature_max() {
+    use tx592::{Channel, Message};
+    assert_eq!(DeciCelsius::new(1047), tx592::TEMPERATURE_MAX);
+    let message = Message::new(Channel::A, 0, 0x123, true, 45, tx592::TEMPERATURE_MAX).unwrap();
+    let bytes = message.to_bytes();
+    // 2047 = 0x7ff, which fills the 11-bit field without touching bit 4
+    assert_eq!(0x0f, bytes[4] & 0x7f);
+    assert_eq!(0x7f, bytes[5] & 0x7f);
+    assert_eq!(Ok(message), Message::from_bytes(bytes));
+}
+
+#[test]
+fn test_592txr_round_trip() {
+    use tx592::{Channel, Message};
+    for channel in [Channel::A, Channel::B, Channel::C] {
+        for sequence in 0..=tx592::SEQUENCE_MAX {
+            for (id, battery_ok, humidity) in [(0, true, 0), (0x5a5, false, 57), (0xfff, true, 100)]
+            {
+                for temperature in [
+                    tx592::TEMPERATURE_MIN,
+                    DeciCelsius::new(-254),
+                    DeciCelsius::ZERO,
+                    DeciCelsius::new(310),
+                    tx592::TEMPERATURE_MAX,
+                ] {
+                    let message =
+                        Message::new(channel, sequence, id, battery_ok, humidity, temperature)
+                            .unwrap();
+                    assert_eq!(Ok(message), Message::from_bytes(message.to_bytes()));
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_592txr_from_bytes_errors() {
+    use tx592::{Error, Message};
+    assert_eq!(
+        Err(Error::Checksum {
+            expected: 0x3d,
+            actual: 0x3e
+        }),
+        Message::from_bytes([0xd1, 0x23, 0x44, 0x2d, 0x09, 0xcf, 0x3e])
+    );
+    // Flip one temperature bit and fix up the checksum
+    assert_eq!(
+        Err(Error::Parity(5)),
+        Message::from_bytes([0xd1, 0x23, 0x44, 0x2d, 0x09, 0xce, 0x3c])
+    );
+    assert_eq!(
+        Err(Error::Channel(0b01)),
+        Message::from_bytes([0x51, 0x23, 0x44, 0x2d, 0x09, 0xcf, 0xbd])
+    );
+    // Wind/rain message type from a 5-in-1
+    assert_eq!(
+        Err(Error::MessageType(0x31)),
+        Message::from_bytes([0xd1, 0x23, 0x71, 0x2d, 0x09, 0xcf, 0x6a])
+    );
+}
+
+#[test]
+fn test_592txr_receiver_jitter() {
+    let messages = [-1000, -254, 0, 310, 1047].map(|temperature| {
+        tx592::Message::new(
+            tx592::Channel::B,
+            2,
+            0x5a5,
+            true,
+            63,
+            DeciCelsius::new(temperature),
+        )
+        .unwrap()
+    });
+    assert_receives_with_jitter(
+        &mut Lcg(2),
+        &mut tx592::receiver(25),
+        &messages,
+        tx592::Message::pulses,
+        tx592::REPEATS,
+        15,
+    );
+}
+
+#[test]
+fn test_609txc_message() {
+    let message = tx609::Message::new(0x5a, true, 45, DeciCelsius::new(231)).unwrap();
+    assert_eq!([0x5a, 0x20, 0xe7, 0x2d, 0x8e], message.to_bytes());
+}
+
+#[test]
+fn test_609txc_message_low_battery() {
+    let message = tx609::Message::new(0x5a, false, 45, DeciCelsius::new(-231)).unwrap();
+    assert_eq!([0x5a, 0xaf, 0x19, 0x2d, 0x4f], message.to_bytes());
+}
+
+#[test]
+fn test_609txc_message_range() {
+    use tx609::{Error, Message};
+    assert_eq!(
+        Err(Error::HumidityRange(101)),
+        Message::new(0x5a, true, 101, DeciCelsius::ZERO)
+    );
+    assert_eq!(
+        Err(Error::TemperatureRange(DeciCelsius::new(-2049))),
+        Message::new(0x5a, true, 0, DeciCelsius::new(-2049))
+    );
+    assert_eq!(
+        Err(Error::TemperatureRange(DeciCelsius::new(2048))),
+        Message::new(0x5a, true, 0, DeciCelsius::new(2048))
+    );
+}
+
+#[test]
+fn test_609txc_round_trip() {
+    use tx609::Message;
+    for (id, battery_ok, humidity) in [(0x00, true, 0), (0x5a, false, 57), (0xff, true, 100)] {
+        for temperature in [
+            tx609::TEMPERATURE_MIN,
+            DeciCelsius::new(-254),
+            DeciCelsius::ZERO,
+            DeciCelsius::new(310),
+            tx609::TEMPERATURE_MAX,
+        ] {
+            let message = Message::new(id, battery_ok, humidity, temperature).unwrap();
+            assert_eq!(Ok(message), Message::from_bytes(message.to_bytes()));
+        }
+    }
+}
+
+/// Frames laid out as documented by rtl_433's acurite_th decoder, where the
+/// status nibble of genuine sensors is 0x2, and 0xa with a low battery. They
+/// must be reproduced byte for byte.
+#[test]
+fn test_609txc_rtl_433_frames() {
+    use tx609::Message;
+    let bytes = [0x5a, 0x20, 0xe7, 0x2d, 0x8e];
+    let message = Message::from_bytes(bytes).unwrap();
+    assert_eq!(0x5a, message.id());
+    assert!(message.battery_ok());
+    assert_eq!(DeciCelsius::new(231), message.temperature());
+    assert_eq!(45, message.humidity());
+    assert_eq!(bytes, message.to_bytes());
+
+    let bytes = [0x5a, 0xaf, 0x19, 0x2d, 0x4f];
+    let message = Message::from_bytes(bytes).unwrap();
+    assert_eq!(0x5a, message.id());
+    assert!(!message.battery_ok());
+    assert_eq!(DeciCelsius::new(-231), message.temperature());
+    assert_eq!(45, message.humidity());
+    assert_eq!(bytes, message.to_bytes());
+}
+
+#[test]
+fn test_609txc_from_bytes_errors() {
+    use tx609::{Error, Message};
+    assert_eq!(
+        Err(Error::Checksum {
+            expected: 0x6e,
+            actual: 0x6f
+        }),
+        Message::from_bytes([0x5a, 0x00, 0xe7, 0x2d, 0x6f])
+    );
+    assert_eq!(
+        Err(Error::HumidityRange(0xad)),
+        Message::from_bytes([0x5a, 0x00, 0xe7, 0xad, 0xee])
+    );
+}
+
+#[test]
+fn test_609txc_receiver_jitter() {
+    let messages = [-2048, -254, 0, 310, 2047].map(|temperature| {
+        tx609::Message::new(0xa5, temperature > 0, 63, DeciCelsius::new(temperature)).unwrap()
+    });
+    assert_receives_with_jitter(
+        &mut Lcg(3),
+        &mut tx609::receiver(25),
+        &messages,
+        tx609::Message::pulses,
+        tx609::REPEATS,
+        15,
+    );
+}
+
+#[test]
+fn test_crc8() {
+    // CRC-8/SMBUS check value
+    assert_eq!(0xf4, crc8(b"123456789", 0x07, 0x00));
+    assert_eq!(0x00, crc8(&[], 0x07, 0x00));
+}
+
+#[test]
+fn test_crc8le() {
+    // CRC-8/ROHC check value
+    assert_eq!(0xd0, crc8le(b"123456789", 0x07, 0xff));
+    let data = [0x22, 0x12, 0x34, 0x00];
+    assert_eq!(
+        crc8(&data.map(u8::reverse_bits), 0x07, 0x00).reverse_bits(),
+        crc8le(&data, 0x07, 0x00)
+    );
+}
+
+#[test]
+fn test_sum8() {
+    assert_eq!(0x3d, sum8(&[0xd1, 0x23, 0x44, 0x2d, 0x09, 0xcf]));
+}
+
+#[test]
+fn test_deci_celsius_to_fahrenheit() {
+    assert_eq!(32, DeciCelsius::new(0).to_fahrenheit());
+    assert_eq!(212, DeciCelsius::new(1000).to_fahrenheit());
+    assert_eq!(-40, DeciCelsius::new(-400).to_fahrenheit());
+    assert_eq!(33, DeciCelsius::new(8).to_fahrenheit()); // 33.44
+    assert_eq!(35, DeciCelsius::new(14).to_fahrenheit()); // 34.52
+    assert_eq!(0, DeciCelsius::new(-178).to_fahrenheit()); // -0.04
+    assert_eq!(-1, DeciCelsius::new(-181).to_fahrenheit()); // -0.58
+    assert_eq!(-5, DeciCelsius::new(-206).to_fahrenheit()); // -5.08
+}
+
+#[test]
+fn test_986_message() {
+    let message = tx986::Message::new(tx986::Sensor::Refrigerator, 0x1234, true, 34).unwrap();
+    assert_eq!([0x22, 0x12, 0x34, 0x00, 0x62], message.to_bytes());
+}
+
+#[test]
+fn test_986_message_freezer_low_battery() {
+    let message = tx986::Message::new(tx986::Sensor::Freezer, 0x1234, false, -5).unwrap();
+    assert_eq!([0x85, 0x12, 0x34, 0x03, 0x44], message.to_bytes());
+}
+
+#[test]
+fn test_986_from_celsius() {
+    let message =
+        tx986::Message::from_celsius(tx986::Sensor::Freezer, 0x1234, true, DeciCelsius::new(-206))
+            .unwrap();
+    assert_eq!(-5, message.temperature());
+    assert_eq!(DeciCelsius::new(-206), message.temperature_celsius());
+}
+
+#[test]
+fn test_986_message_range() {
+    use tx986::{Error, Message, Sensor};
+    assert_eq!(
+        Err(Error::TemperatureRange(-128)),
+        Message::new(Sensor::Freezer, 0, true, -128)
+    );
+    assert_eq!(
+        Err(Error::TemperatureRange(128)),
+        Message::new(Sensor::Freezer, 0, true, 128)
+    );
+}
+
+#[test]
+fn test_986_round_trip() {
+    use tx986::{Message, Sensor};
+    for sensor in [Sensor::Refrigerator, Sensor::Freezer] {
+        for (id, battery_ok) in [(0x0000, true), (0x1234, false), (0xffff, true)] {
+            for temperature in [tx986::TEMPERATURE_MIN, -5, 0, 34, tx986::TEMPERATURE_MAX] {
+                let message = Message::new(sensor, id, battery_ok, temperature).unwrap();
+                assert_eq!(Ok(message), Message::from_bytes(message.to_bytes()));
+            }
+        }
+    }
+}
+
+#[test]
+fn test_986_from_bytes_errors() {
+    use tx986::{Error, Message};
+    assert_eq!(
+        Err(Error::Crc {
+            expected: 0x62,
+            actual: 0x63
+        }),
+        Message::from_bytes([0x22, 0x12, 0x34, 0x00, 0x63])
+    );
+    let bytes = [0x22, 0x12, 0x34, 0x10];
+    assert_eq!(
+        Err(Error::ReservedBits(0x10)),
+        Message::from_bytes([
+            bytes[0],
+            bytes[1],
+            bytes[2],
+            bytes[3],
+            crc8le(&bytes, 0x07, 0x00)
+        ])
+    );
+}
+
+#[test]
+fn test_986_pulses_lsb_first() {
+    let message = tx986::Message::new(tx986::Sensor::Refrigerator, 0x1234, true, 34).unwrap();
+    let mut pulses = message.pulses().skip(4);
+    // 0x22, LSB first
+    for bit in [0, 1, 0, 0, 0, 1, 0, 0] {
+        let space_us = if bit != 0 { 880 } else { 520 };
+        assert_eq!(Some(pulse::Pulse::new(400, space_us)), pulses.next());
+    }
+}
+
+/// Check a message the way rtl_433 does: undo the LSB first order of the
+/// received bytes, check the reflected CRC and read the fields
+#[test]
+fn test_986_rtl_433_decode() {
+    let message = tx986::Message::new(tx986::Sensor::Freezer, 0x1234, false, -5).unwrap();
+    let mut decoder = pulse::Decoder::<5>::new(tx986::ENCODING, 0);
+    let received = message
+        .pulses()
+        .find_map(|pulse| decoder.feed(pulse))
+        .unwrap();
+    // The CRC is a plain CRC-8 of the bytes in the order they are received
+    assert_eq!([0xa1, 0x48, 0x2c, 0xc0, 0x22], received);
+    assert_eq!(received[4], crc8(&received[..4], 0x07, 0x00));
+
+    let br = received.map(u8::reverse_bits);
+    assert_eq!(br[4], crc8le(&br[..4], 0x07, 0x00));
+    assert_eq!(0x85, br[0]); // sign and magnitude
+    assert_eq!(0x1234, u16::from_be_bytes([br[1], br[2]]));
+    assert_eq!(0x03, br[3]); // sensor 2, battery low
+}
+
+#[test]
+fn test_986_receiver_jitter() {
+    let messages = [-127, -5, 0, 34, 127].map(|temperature| {
+        tx986::Message::new(tx986::Sensor::Freezer, 0xbeef, true, temperature).unwrap()
+    });
+    assert_receives_with_jitter(
+        &mut Lcg(4),
+        &mut tx986::receiver(20),
+        &messages,
+        tx986::Message::pulses,
+        tx986::REPEATS,
+        10,
+    );
+}
+
+#[test]
+fn test_5n1_wind_rain() {
+    use tx5n1::{Message, Reading};
+    let message = Message::new(
+        tx592::Channel::A,
+        2,
+        0x3a5,
+        true,
+        Reading::WindRain {
+            wind_speed: 0x9b,
+            wind_direction: 5,
+            rain: 1234,
+        },
+    )
+    .unwrap();
+    let bytes = [0xe3, 0xa5, 0x71, 0x93, 0x35, 0x09, 0xd2, 0x9c];
+    assert_eq!(bytes, message.to_bytes());
+    assert_eq!(Ok(message), Message::from_bytes(bytes));
+}
+
+#[test]
+fn test_5n1_rain_above_13_bits() {
+    use tx5n1::{Message, Reading};
+    let message = Message::new(
+        tx592::Channel::A,
+        0,
+        0x3a5,
+        true,
+        Reading::WindRain {
+            wind_speed: 0,
+            wind_direction: 0,
+            rain: 12345,
+        },
+    )
+    .unwrap();
+    let bytes = message.to_bytes();
+    // The top bit of the counter is in the third data byte
+    assert_eq!(0x60, bytes[5] & 0x7f);
+    assert_eq!(Ok(message), Message::from_bytes(bytes));
+}
+
+#[test]
+fn test_5n1_wind_temperature_humidity() {
+    use tx5n1::{Message, Reading};
+    let message = Message::new(
+        tx592::Channel::B,
+        1,
+        0x3a5,
+        false,
+        Reading::WindTemperatureHumidity {
+            wind_speed: 12,
+            temperature: 723,
+            humidity: 45,
+        },
+    )
+    .unwrap();
+    let bytes = [0x93, 0xa5, 0xb8, 0x81, 0x48, 0x63, 0x2d, 0x49];
+    assert_eq!(bytes, message.to_bytes());
+    assert_eq!(Ok(message), Message::from_bytes(bytes));
+}
+
+#[test]
+fn test_3n1_temperature_humidity_wind() {
+    use tx5n1::{Message, Reading};
+    let message = Message::new(
+        tx592::Channel::C,
+        0,
+        0x012,
+        true,
+        Reading::TemperatureHumidityWind {
+            temperature: -123,
+            humidity: 88,
+            wind_speed: 17,
+        },
+    )
+    .unwrap();
+    let bytes = [0x00, 0x12, 0x60, 0xd8, 0x09, 0x7d, 0x11, 0xe1];
+    assert_eq!(bytes, message.to_bytes());
+    assert_eq!(Ok(message), Message::from_bytes(bytes));
+}
+
+#[test]
+fn test_5n1_temperature() {
+    let reading = tx5n1::Reading::WindTemperatureHumidity {
+        wind_speed: 12,
+        temperature: 723,
+        humidity: 45,
+    };
+    assert_eq!(Some(DeciCelsius::new(224)), reading.temperature());
+    let reading = tx5n1::Reading::WindRain {
+        wind_speed: 0,
+        wind_direction: 0,
+        rain: 0,
+    };
+    assert_eq!(None, reading.temperature());
+}
+
+#[test]
+fn test_5n1_message_range() {
+    use tx5n1::{Error, Message, Reading};
+    let wind_rain = |wind_direction, rain| Reading::WindRain {
+        wind_speed: 0,
+        wind_direction,
+        rain,
+    };
+    assert_eq!(
+        Err(Error::SequenceRange(4)),
+        Message::new(tx592::Channel::A, 4, 0, true, wind_rain(0, 0))
+    );
+    assert_eq!(
+        Err(Error::IdRange(0x1000)),
+        Message::new(tx592::Channel::A, 0, 0x1000, true, wind_rain(0, 0))
+    );
+    assert_eq!(
+        Err(Error::WindDirectionRange(16)),
+        Message::new(tx592::Channel::A, 0, 0, true, wind_rain(16, 0))
+    );
+    assert_eq!(
+        Err(Error::RainRange(0x4000)),
+        Message::new(tx592::Channel::A, 0, 0, true, wind_rain(0, 0x4000))
+    );
+    assert_eq!(
+        Err(Error::TemperatureRange(1648)),
+        Message::new(
+            tx592::Channel::A,
+            0,
+            0,
+            true,
+            Reading::WindTemperatureHumidity {
+                wind_speed: 0,
+                temperature: 1648,
+                humidity: 0
+            }
+        )
+    );
+    assert_eq!(
+        Err(Error::HumidityRange(101)),
+        Message::new(
+            tx592::Channel::A,
+            0,
+            0,
+            true,
+            Reading::WindTemperatureHumidity {
+                wind_speed: 0,
+                temperature: 0,
+                humidity: 101
+            }
+        )
+    );
+    assert_eq!(
+        Err(Error::TemperatureRange(-1401)),
+        Message::new(
+            tx592::Channel::A,
+            0,
+            0,
+            true,
+            Reading::TemperatureHumidityWind {
+                temperature: -1401,
+                humidity: 0,
+                wind_speed: 0
+            }
+        )
+    );
+    assert_eq!(
+        Err(Error::WindSpeedRange(128)),
+        Message::new(
+            tx592::Channel::A,
+            0,
+            0,
+            true,
+            Reading::TemperatureHumidityWind {
+                temperature: 0,
+                humidity: 0,
+                wind_speed: 128
+            }
+        )
+    );
+}
+
+#[test]
+fn test_5n1_round_trip() {
+    use tx5n1::{Message, Reading};
+    let readings = [
+        Reading::WindRain {
+            wind_speed: 0,
+            wind_direction: 0,
+            rain: 0,
+        },
+        Reading::WindRain {
+            wind_speed: 0xff,
+            wind_direction: tx5n1::WIND_DIRECTION_MAX,
+            rain: tx5n1::RAIN_MAX,
+        },
+        Reading::WindTemperatureHumidity {
+            wind_speed: 0,
+            temperature: tx5n1::TEMPERATURE_5N1_MIN,
+            humidity: 0,
+        },
+        Reading::WindTemperatureHumidity {
+            wind_speed: 0xff,
+            temperature: tx5n1::TEMPERATURE_5N1_MAX,
+            humidity: tx5n1::HUMIDITY_MAX,
+        },
+        Reading::TemperatureHumidityWind {
+            temperature: tx5n1::TEMPERATURE_3N1_MIN,
+            humidity: 0,
+            wind_speed: 0,
+        },
+        Reading::TemperatureHumidityWind {
+            temperature: tx5n1::TEMPERATURE_3N1_MAX,
+            humidity: tx5n1::HUMIDITY_MAX,
+            wind_speed: tx5n1::WIND_SPEED_MPH_MAX,
+        },
+    ];
+    for reading in readings {
+        for (sequence, id, battery_ok) in [(0, 0x000, true), (3, 0xfff, false)] {
+            let message =
+                Message::new(tx592::Channel::B, sequence, id, battery_ok, reading).unwrap();
+            assert_eq!(Ok(message), Message::from_bytes(message.to_bytes()));
+        }
+    }
+}
+
+#[test]
+fn test_5n1_from_bytes_errors() {
+    use tx5n1::{Error, Message};
+    assert_eq!(
+        Err(Error::Checksum {
+            expected: 0x9c,
+            actual: 0x9d
+        }),
+        Message::from_bytes([0xe3, 0xa5, 0x71, 0x93, 0x35, 0x09, 0xd2, 0x9d])
+    );
+    // Flip one rain bit and fix up the checksum
+    assert_eq!(
+        Err(Error::Parity(6)),
+        Message::from_bytes([0xe3, 0xa5, 0x71, 0x93, 0x35, 0x09, 0xd3, 0x9d])
+    );
+    // 592TXR message type
+    assert_eq!(
+        Err(Error::MessageType(0x04)),
+        Message::from_bytes([0xe3, 0xa5, 0x44, 0x93, 0x35, 0x09, 0xd2, 0x6f])
+    );
+}
+
+#[test]
+fn test_5n1_receiver_jitter() {
+    use tx5n1::{Message, Reading};
+    let messages = [
+        Message::new(
+            tx592::Channel::A,
+            0,
+            0x3a5,
+            true,
+            Reading::WindRain {
+                wind_speed: 0x9b,
+                wind_direction: 5,
+                rain: 1234,
+            },
+        )
+        .unwrap(),
+        Message::new(
+            tx592::Channel::A,
+            1,
+            0x3a5,
+            true,
+            Reading::WindTemperatureHumidity {
+                wind_speed: 12,
+                temperature: 723,
+                humidity: 45,
+            },
+        )
+        .unwrap(),
+    ];
+    assert_receives_with_jitter(
+        &mut Lcg(5),
+        &mut tx5n1::receiver(25),
+        &messages,
+        Message::pulses,
+        tx5n1::REPEATS,
+        15,
+    );
+}
@@ -0,0 +1,233 @@
+//! Support for the Acurite 592TXR temperature and humidity sensor.
+//!
+//! Each message is 7 bytes:
+//!
+//! ```text
+//! CCSS IIII | IIII IIII | pB00 0100 | pHHH HHHH | p000 TTTT | pTTT TTTT | KKKK KKKK
+//! ```
+//!
+//! - C: channel
+//! - S: message sequence counter
+//! - I: transmitter ID
+//! - B: battery OK
+//! - H: relative humidity (%)
+//! - T: 11-bit temperature in tenths of a degree Celsius, offset by 1000
+//! - p: even parity of the byte
+//! - K: sum of the previous bytes
+
+use crate::{
+    pulse::{self, Encoding, Pulse, Pulses},
+    sum8, with_parity, DeciCelsius,
+};
+
+/// Timing of the radio pulses. Each message starts with four sync pulses, and
+/// bits are distinguished by the length of the pulse.
+pub const ENCODING: Encoding = Encoding {
+    sync: Pulse::new(600, 600),
+    sync_count: 4,
+    zero: Pulse::new(400, 200),
+    one: Pulse::new(200, 400),
+    stop: None,
+};
+
+/// Number of times each message is sent
+pub const REPEATS: usize = 3;
+
+/// Lowest temperature that can be sent
+pub const TEMPERATURE_MIN: DeciCelsius = DeciCelsius::new(-TEMPERATURE_OFFSET);
+/// Highest temperature that can be sent
+pub const TEMPERATURE_MAX: DeciCelsius = DeciCelsius::new(0x07ff - TEMPERATURE_OFFSET);
+const TEMPERATURE_OFFSET: i16 = 1000;
+
+pub const ID_MAX: u16 = 0x0fff;
+pub const SEQUENCE_MAX: u8 = 0b11;
+pub const HUMIDITY_MAX: u8 = 100;
+
+const MESSAGE_TYPE: u8 = 0x04;
+const STATUS_BATTERY_OK: u8 = 1 << 6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    TemperatureRange(DeciCelsius),
+    HumidityRange(u8),
+    IdRange(u16),
+    SequenceRange(u8),
+    /// The channel bits do not correspond to a valid channel.
+    Channel(u8),
+    /// The message is not a temperature and humidity message.
+    MessageType(u8),
+    /// The byte at this index has odd parity.
+    Parity(usize),
+    /// The last byte does not match the sum of the rest of the message.
+    Checksum {
+        expected: u8,
+        actual: u8,
+    },
+}
+
+/// Channel selected by the switch on the sensor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Channel {
+    A,
+    B,
+    C,
+}
+
+impl Channel {
+    pub(crate) const fn bits(self) -> u8 {
+        match self {
+            Self::A => 0b11,
+            Self::B => 0b10,
+            Self::C => 0b00,
+        }
+    }
+
+    pub(crate) const fn from_bits(bits: u8) -> Option<Self> {
+        match bits {
+            0b11 => Some(Self::A),
+            0b10 => Some(Self::B),
+            0b00 => Some(Self::C),
+            _ => None,
+        }
+    }
+}
+
+/// A single 592TXR message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Message {
+    channel: Channel,
+    sequence: u8,
+    id: u16,
+    battery_ok: bool,
+    humidity: u8,
+    temperature: DeciCelsius,
+}
+
+impl Message {
+    /// Create a message with a relative humidity in percent. Fails if any
+    /// field does not fit in the message.
+    pub const fn new(
+        channel: Channel,
+        sequence: u8,
+        id: u16,
+        battery_ok: bool,
+        humidity: u8,
+        temperature: DeciCelsius,
+    ) -> Result<Self, Error> {
+        if sequence > SEQUENCE_MAX {
+            return Err(Error::SequenceRange(sequence));
+        }
+        if id > ID_MAX {
+            return Err(Error::IdRange(id));
+        }
+        if humidity > HUMIDITY_MAX {
+            return Err(Error::HumidityRange(humidity));
+        }
+        if temperature.tenths() < TEMPERATURE_MIN.tenths()
+            || temperature.tenths() > TEMPERATURE_MAX.tenths()
+        {
+            return Err(Error::TemperatureRange(temperature));
+        }
+        Ok(Self {
+            channel,
+            sequence,
+            id,
+            battery_ok,
+            humidity,
+            temperature,
+        })
+    }
+
+    pub const fn channel(&self) -> Channel {
+        self.channel
+    }
+
+    pub const fn sequence(&self) -> u8 {
+        self.sequence
+    }
+
+    pub const fn id(&self) -> u16 {
+        self.id
+    }
+
+    pub const fn battery_ok(&self) -> bool {
+        self.battery_ok
+    }
+
+    /// Relative humidity in percent
+    pub const fn humidity(&self) -> u8 {
+        self.humidity
+    }
+
+    pub const fn temperature(&self) -> DeciCelsius {
+        self.temperature
+    }
+
+    pub fn to_bytes(&self) -> [u8; 7] {
+        let status = if self.battery_ok {
+            STATUS_BATTERY_OK
+        } else {
+            0
+        };
+        let temperature = (self.temperature.tenths() + TEMPERATURE_OFFSET) as u16;
+        let [id_high, id_low] = self.id.to_be_bytes();
+        let mut bytes = [
+            self.channel.bits() << 6 | self.sequence << 4 | id_high,
+            id_low,
+            with_parity(status | MESSAGE_TYPE),
+            with_parity(self.humidity),
+            with_parity((temperature >> 7) as u8),
+            with_parity(temperature as u8),
+            0,
+        ];
+        bytes[6] = sum8(&bytes[..6]);
+        bytes
+    }
+
+    /// Decode a received message, verifying its parity and checksum.
+    pub fn from_bytes(bytes: [u8; 7]) -> Result<Self, Error> {
+        let expected = sum8(&bytes[..6]);
+        if bytes[6] != expected {
+            return Err(Error::Checksum {
+                expected,
+                actual: bytes[6],
+            });
+        }
+        for (i, byte) in bytes.iter().enumerate().take(6).skip(2) {
+            if byte.count_ones() % 2 != 0 {
+                return Err(Error::Parity(i));
+            }
+        }
+
+        let message_type = bytes[2] & 0x3f;
+        if message_type != MESSAGE_TYPE {
+            return Err(Error::MessageType(message_type));
+        }
+
+        let temperature =
+            ((bytes[4] as i16 & 0x0f) << 7 | (bytes[5] as i16 & 0x7f)) - TEMPERATURE_OFFSET;
+
+        Self::new(
+            Channel::from_bits(bytes[0] >> 6).ok_or(Error::Channel(bytes[0] >> 6))?,
+            (bytes[0] >> 4) & 0b11,
+            u16::from_be_bytes([bytes[0] & 0x0f, bytes[1]]),
+            bytes[2] & STATUS_BATTERY_OK != 0,
+            bytes[3] & 0x7f,
+            DeciCelsius::new(temperature),
+        )
+    }
+
+    /// Radio pulses to transmit this message
+    pub fn pulses(&self) -> Pulses<7> {
+        ENCODING.encode(self.to_bytes())
+    }
+}
+
+/// Receives 592TXR messages from radio pulses
+pub type Receiver = pulse::Receiver<fn([u8; 7]) -> Result<Message, Error>, 7>;
+
+/// Create a receiver that accepts pulses within `tolerance_pct` percent of the
+/// nominal timing.
+pub const fn receiver(tolerance_pct: u8) -> Receiver {
+    Receiver::new(ENCODING, tolerance_pct, Message::from_bytes)
+}
@@ -0,0 +1,368 @@
+//! Support for the Acurite 5-in-1 and 3-in-1 weather station sensors.
+//!
+//! Each message is 8 bytes, with a common header followed by one of several
+//! message types:
+//!
+//! ```text
+//! CCSS IIII | IIII IIII | pBMM MMMM | pXXX XXXX | pXXX XXXX | pXXX XXXX | pXXX XXXX | KKKK KKKK
+//! ```
+//!
+//! - C: channel
+//! - S: message sequence counter
+//! - I: transmitter ID
+//! - B: battery OK
+//! - M: message type
+//! - X: message data, see [`Reading`]
+//! - p: even parity of the byte
+//! - K: sum of the previous bytes
+
+use crate::{
+    pulse::{self, Encoding, Pulses},
+    sum8,
+    tx592::{self, Channel},
+    with_parity, DeciCelsius,
+};
+
+/// Timing of the radio pulses, which is the same as the 592TXR.
+pub const ENCODING: Encoding = tx592::ENCODING;
+
+/// Number of times each message is sent
+pub const REPEATS: usize = 3;
+
+pub const ID_MAX: u16 = 0x0fff;
+pub const SEQUENCE_MAX: u8 = 0b11;
+pub const HUMIDITY_MAX: u8 = 100;
+pub const WIND_DIRECTION_MAX: u8 = 0x0f;
+pub const RAIN_MAX: u16 = 0x3fff;
+/// Highest wind speed reported by the 3-in-1, in miles per hour
+pub const WIND_SPEED_MPH_MAX: u8 = 0x7f;
+
+/// Lowest temperature reported by the 5-in-1, in tenths of a degree Fahrenheit
+pub const TEMPERATURE_5N1_MIN: i16 = -TEMPERATURE_5N1_OFFSET;
+/// Highest temperature reported by the 5-in-1, in tenths of a degree
+/// Fahrenheit
+pub const TEMPERATURE_5N1_MAX: i16 = 0x07ff - TEMPERATURE_5N1_OFFSET;
+const TEMPERATURE_5N1_OFFSET: i16 = 400;
+
+/// Lowest temperature reported by the 3-in-1, in tenths of a degree Fahrenheit
+pub const TEMPERATURE_3N1_MIN: i16 = -TEMPERATURE_3N1_OFFSET;
+/// Highest temperature reported by the 3-in-1, in tenths of a degree
+/// Fahrenheit
+pub const TEMPERATURE_3N1_MAX: i16 = 0x0fff - TEMPERATURE_3N1_OFFSET;
+const TEMPERATURE_3N1_OFFSET: i16 = 1400;
+
+const MESSAGE_TYPE_WIND_RAIN: u8 = 0x31;
+const MESSAGE_TYPE_WIND_TEMPERATURE_HUMIDITY: u8 = 0x38;
+const MESSAGE_TYPE_3N1: u8 = 0x20;
+
+const STATUS_BATTERY_OK: u8 = 1 << 6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    TemperatureRange(i16),
+    HumidityRange(u8),
+    WindSpeedRange(u8),
+    WindDirectionRange(u8),
+    RainRange(u16),
+    IdRange(u16),
+    SequenceRange(u8),
+    /// The channel bits do not correspond to a valid channel.
+    Channel(u8),
+    /// The message type is not one of the supported types.
+    MessageType(u8),
+    /// The byte at this index has odd parity.
+    Parity(usize),
+    /// The last byte does not match the sum of the rest of the message.
+    Checksum {
+        expected: u8,
+        actual: u8,
+    },
+}
+
+/// Measurements contained in a message. The 5-in-1 alternates between the two
+/// types of message. Temperatures are sent in Fahrenheit, which can be
+/// converted using [`DeciCelsius::to_deci_fahrenheit`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reading {
+    /// 5-in-1 message type 0x31
+    WindRain {
+        /// Raw anemometer reading
+        wind_speed: u8,
+        /// Raw wind vane position
+        wind_direction: u8,
+        /// 14-bit rain gauge tip counter, in hundredths of an inch
+        rain: u16,
+    },
+    /// 5-in-1 message type 0x38
+    WindTemperatureHumidity {
+        /// Raw anemometer reading
+        wind_speed: u8,
+        /// Temperature in tenths of a degree Fahrenheit
+        temperature: i16,
+        /// Relative humidity in percent
+        humidity: u8,
+    },
+    /// 3-in-1 message type 0x20
+    TemperatureHumidityWind {
+        /// Temperature in tenths of a degree Fahrenheit
+        temperature: i16,
+        /// Relative humidity in percent
+        humidity: u8,
+        /// Wind speed in miles per hour
+        wind_speed: u8,
+    },
+}
+
+impl Reading {
+    /// Temperature contained in the message, if any
+    pub const fn temperature(&self) -> Option<DeciCelsius> {
+        match *self {
+            Self::WindTemperatureHumidity { temperature, .. }
+            | Self::TemperatureHumidityWind { temperature, .. } => {
+                Some(DeciCelsius::from_deci_fahrenheit(temperature))
+            }
+            Self::WindRain { .. } => None,
+        }
+    }
+
+    const fn validate(&self) -> Result<(), Error> {
+        match *self {
+            Self::WindRain {
+                wind_direction,
+                rain,
+                ..
+            } => {
+                if wind_direction > WIND_DIRECTION_MAX {
+                    return Err(Error::WindDirectionRange(wind_direction));
+                }
+                if rain > RAIN_MAX {
+                    return Err(Error::RainRange(rain));
+                }
+            }
+            Self::WindTemperatureHumidity {
+                temperature,
+                humidity,
+                ..
+            } => {
+                if humidity > HUMIDITY_MAX {
+                    return Err(Error::HumidityRange(humidity));
+                }
+                if temperature < TEMPERATURE_5N1_MIN || temperature > TEMPERATURE_5N1_MAX {
+                    return Err(Error::TemperatureRange(temperature));
+                }
+            }
+            Self::TemperatureHumidityWind {
+                temperature,
+                humidity,
+                wind_speed,
+            } => {
+                if humidity > HUMIDITY_MAX {
+                    return Err(Error::HumidityRange(humidity));
+                }
+                if temperature < TEMPERATURE_3N1_MIN || temperature > TEMPERATURE_3N1_MAX {
+                    return Err(Error::TemperatureRange(temperature));
+                }
+                if wind_speed > WIND_SPEED_MPH_MAX {
+                    return Err(Error::WindSpeedRange(wind_speed));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    const fn message_type(&self) -> u8 {
+        match self {
+            Self::WindRain { .. } => MESSAGE_TYPE_WIND_RAIN,
+            Self::WindTemperatureHumidity { .. } => MESSAGE_TYPE_WIND_TEMPERATURE_HUMIDITY,
+            Self::TemperatureHumidityWind { .. } => MESSAGE_TYPE_3N1,
+        }
+    }
+
+    /// Data bytes, without parity
+    const fn to_bytes(self) -> [u8; 4] {
+        match self {
+            Self::WindRain {
+                wind_speed,
+                wind_direction,
+                rain,
+            } => [
+                wind_speed >> 3,
+                (wind_speed & 0x07) << 4 | wind_direction,
+                (rain >> 7) as u8,
+                rain as u8 & 0x7f,
+            ],
+            Self::WindTemperatureHumidity {
+                wind_speed,
+                temperature,
+                humidity,
+            } => {
+                let temperature = (temperature + TEMPERATURE_5N1_OFFSET) as u16;
+                [
+                    wind_speed >> 3,
+                    (wind_speed & 0x07) << 4 | (temperature >> 7) as u8,
+                    temperature as u8 & 0x7f,
+                    humidity,
+                ]
+            }
+            Self::TemperatureHumidityWind {
+                temperature,
+                humidity,
+                wind_speed,
+            } => {
+                let temperature = (temperature + TEMPERATURE_3N1_OFFSET) as u16;
+                [
+                    humidity,
+                    (temperature >> 7) as u8,
+                    temperature as u8 & 0x7f,
+                    wind_speed,
+                ]
+            }
+        }
+    }
+
+    const fn from_bytes(message_type: u8, data: [u8; 4]) -> Result<Self, Error> {
+        let [a, b, c, d] = [
+            data[0] & 0x7f,
+            data[1] & 0x7f,
+            data[2] & 0x7f,
+            data[3] & 0x7f,
+        ];
+        match message_type {
+            MESSAGE_TYPE_WIND_RAIN => Ok(Self::WindRain {
+                wind_speed: (a & 0x1f) << 3 | b >> 4,
+                wind_direction: b & 0x0f,
+                rain: (c as u16) << 7 | d as u16,
+            }),
+            MESSAGE_TYPE_WIND_TEMPERATURE_HUMIDITY => Ok(Self::WindTemperatureHumidity {
+                wind_speed: (a & 0x1f) << 3 | b >> 4,
+                temperature: (((b & 0x0f) as i16) << 7 | c as i16) - TEMPERATURE_5N1_OFFSET,
+                humidity: d,
+            }),
+            MESSAGE_TYPE_3N1 => Ok(Self::TemperatureHumidityWind {
+                temperature: (((b & 0x1f) as i16) << 7 | c as i16) - TEMPERATURE_3N1_OFFSET,
+                humidity: a,
+                wind_speed: d,
+            }),
+            _ => Err(Error::MessageType(message_type)),
+        }
+    }
+}
+
+/// A single 5-in-1 or 3-in-1 message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Message {
+    channel: Channel,
+    sequence: u8,
+    id: u16,
+    battery_ok: bool,
+    reading: Reading,
+}
+
+impl Message {
+    /// Create a message, failing if any field does not fit in the message.
+    pub const fn new(
+        channel: Channel,
+        sequence: u8,
+        id: u16,
+        battery_ok: bool,
+        reading: Reading,
+    ) -> Result<Self, Error> {
+        if sequence > SEQUENCE_MAX {
+            return Err(Error::SequenceRange(sequence));
+        }
+        if id > ID_MAX {
+            return Err(Error::IdRange(id));
+        }
+        if let Err(e) = reading.validate() {
+            return Err(e);
+        }
+        Ok(Self {
+            channel,
+            sequence,
+            id,
+            battery_ok,
+            reading,
+        })
+    }
+
+    pub const fn channel(&self) -> Channel {
+        self.channel
+    }
+
+    pub const fn sequence(&self) -> u8 {
+        self.sequence
+    }
+
+    pub const fn id(&self) -> u16 {
+        self.id
+    }
+
+    pub const fn battery_ok(&self) -> bool {
+        self.battery_ok
+    }
+
+    pub const fn reading(&self) -> Reading {
+        self.reading
+    }
+
+    pub fn to_bytes(&self) -> [u8; 8] {
+        let status = if self.battery_ok {
+            STATUS_BATTERY_OK
+        } else {
+            0
+        };
+        let [id_high, id_low] = self.id.to_be_bytes();
+        let data = self.reading.to_bytes();
+        let mut bytes = [
+            self.channel.bits() << 6 | self.sequence << 4 | id_high,
+            id_low,
+            with_parity(status | self.reading.message_type()),
+            with_parity(data[0]),
+            with_parity(data[1]),
+            with_parity(data[2]),
+            with_parity(data[3]),
+            0,
+        ];
+        bytes[7] = sum8(&bytes[..7]);
+        bytes
+    }
+
+    /// Decode a received message, verifying its parity and checksum.
+    pub fn from_bytes(bytes: [u8; 8]) -> Result<Self, Error> {
+        let expected = sum8(&bytes[..7]);
+        if bytes[7] != expected {
+            return Err(Error::Checksum {
+                expected,
+                actual: bytes[7],
+            });
+        }
+        for (i, byte) in bytes.iter().enumerate().take(7).skip(2) {
+            if byte.count_ones() % 2 != 0 {
+                return Err(Error::Parity(i));
+            }
+        }
+
+        let channel = bytes[0] >> 6;
+        Self::new(
+            Channel::from_bits(channel).ok_or(Error::Channel(channel))?,
+            (bytes[0] >> 4) & 0b11,
+            u16::from_be_bytes([bytes[0] & 0x0f, bytes[1]]),
+            bytes[2] & STATUS_BATTERY_OK != 0,
+            Reading::from_bytes(bytes[2] & 0x3f, [bytes[3], bytes[4], bytes[5], bytes[6]])?,
+        )
+    }
+
+    /// Radio pulses to transmit this message
+    pub fn pulses(&self) -> Pulses<8> {
+        ENCODING.encode(self.to_bytes())
+    }
+}
+
+/// Receives 5-in-1 and 3-in-1 messages from radio pulses
+pub type Receiver = pulse::Receiver<fn([u8; 8]) -> Result<Message, Error>, 8>;
+
+/// Create a receiver that accepts pulses within `tolerance_pct` percent of the
+/// nominal timing.
+pub const fn receiver(tolerance_pct: u8) -> Receiver {
+    Receiver::new(ENCODING, tolerance_pct, Message::from_bytes)
+}
@@ -0,0 +1,159 @@
+//! Support for the Acurite 609TXC temperature and humidity sensor.
+//!
+//! Each message is 5 bytes:
+//!
+//! ```text
+//! IIII IIII | B??? TTTT | TTTT TTTT | HHHH HHHH | KKKK KKKK
+//! ```
+//!
+//! - I: transmitter ID
+//! - B: battery low
+//! - ?: unknown, always 010 in genuine messages. Sent as 010 and ignored when
+//!   receiving.
+//! - T: 12-bit signed temperature in tenths of a degree Celsius
+//! - H: relative humidity (%)
+//! - K: sum of the previous bytes
+
+use crate::{
+    pulse::{self, Encoding, Pulse, Pulses},
+    sum8, DeciCelsius,
+};
+
+/// Timing of the radio pulses. Each message starts with a long gap, and bits
+/// are distinguished by the length of the gap following each pulse.
+pub const ENCODING: Encoding = Encoding {
+    sync: Pulse::new(500, 9000),
+    sync_count: 1,
+    zero: Pulse::new(500, 1000),
+    one: Pulse::new(500, 2000),
+    stop: Some(Pulse::new(500, 500)),
+};
+
+/// Number of times each message is sent
+pub const REPEATS: usize = 3;
+
+/// Lowest temperature that fits in the 12-bit temperature field
+pub const TEMPERATURE_MIN: DeciCelsius = DeciCelsius::new(-2048);
+/// Highest temperature that fits in the 12-bit temperature field
+pub const TEMPERATURE_MAX: DeciCelsius = DeciCelsius::new(2047);
+
+pub const HUMIDITY_MAX: u8 = 100;
+
+const STATUS_BATTERY_LOW: u8 = 0b1000;
+/// Unknown status bits, as sent by genuine sensors
+const STATUS_UNKNOWN: u8 = 0b0010;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    TemperatureRange(DeciCelsius),
+    HumidityRange(u8),
+    /// The last byte does not match the sum of the rest of the message.
+    Checksum {
+        expected: u8,
+        actual: u8,
+    },
+}
+
+/// A single 609TXC message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Message {
+    id: u8,
+    battery_ok: bool,
+    humidity: u8,
+    temperature: DeciCelsius,
+}
+
+impl Message {
+    /// Create a message with a relative humidity in percent. Fails if the
+    /// temperature is outside of the range -204.8 to 204.7 °C or the humidity
+    /// is above 100%.
+    pub const fn new(
+        id: u8,
+        battery_ok: bool,
+        humidity: u8,
+        temperature: DeciCelsius,
+    ) -> Result<Self, Error> {
+        if humidity > HUMIDITY_MAX {
+            return Err(Error::HumidityRange(humidity));
+        }
+        if temperature.tenths() < TEMPERATURE_MIN.tenths()
+            || temperature.tenths() > TEMPERATURE_MAX.tenths()
+        {
+            return Err(Error::TemperatureRange(temperature));
+        }
+        Ok(Self {
+            id,
+            battery_ok,
+            humidity,
+            temperature,
+        })
+    }
+
+    pub const fn id(&self) -> u8 {
+        self.id
+    }
+
+    pub const fn battery_ok(&self) -> bool {
+        self.battery_ok
+    }
+
+    /// Relative humidity in percent
+    pub const fn humidity(&self) -> u8 {
+        self.humidity
+    }
+
+    pub const fn temperature(&self) -> DeciCelsius {
+        self.temperature
+    }
+
+    pub fn to_bytes(&self) -> [u8; 5] {
+        let status = if self.battery_ok {
+            STATUS_UNKNOWN
+        } else {
+            STATUS_BATTERY_LOW | STATUS_UNKNOWN
+        };
+        let temperature = self.temperature.tenths();
+        let mut bytes = [
+            self.id,
+            status << 4 | ((temperature >> 8) as u8 & 0x0f),
+            (temperature & 0xff) as u8,
+            self.humidity,
+            0,
+        ];
+        bytes[4] = sum8(&bytes[..4]);
+        bytes
+    }
+
+    /// Decode a received message, verifying its checksum.
+    pub fn from_bytes(bytes: [u8; 5]) -> Result<Self, Error> {
+        let expected = sum8(&bytes[..4]);
+        if bytes[4] != expected {
+            return Err(Error::Checksum {
+                expected,
+                actual: bytes[4],
+            });
+        }
+
+        Self::new(
+            bytes[0],
+            (bytes[1] >> 4) & STATUS_BATTERY_LOW == 0,
+            bytes[3],
+            // Sign extend the 12-bit temperature
+            DeciCelsius::new((i16::from_be_bytes([bytes[1] & 0x0f, bytes[2]]) << 4) >> 4),
+        )
+    }
+
+    /// Radio pulses to transmit this message
+    pub fn pulses(&self) -> Pulses<5> {
+        ENCODING.encode(self.to_bytes())
+    }
+}
+
+/// Receives 609TXC messages from radio pulses
+pub type Receiver = pulse::Receiver<fn([u8; 5]) -> Result<Message, Error>, 5>;
+
+/// Create a receiver that accepts pulses within `tolerance_pct` percent of the
+/// nominal timing.
+pub const fn receiver(tolerance_pct: u8) -> Receiver {
+    Receiver::new(ENCODING, tolerance_pct, Message::from_bytes)
+}
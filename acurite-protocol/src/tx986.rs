@@ -0,0 +1,202 @@
+//! Support for the Acurite 986 refrigerator/freezer sensor.
+//!
+//! Each message is 5 bytes, sent LSB first:
+//!
+//! ```text
+//! TTTT TTTT | IIII IIII | IIII IIII | 0000 00BS | CCCC CCCC
+//! ```
+//!
+//! - T: temperature in degrees Fahrenheit, as sign and magnitude
+//! - I: transmitter ID
+//! - B: battery low
+//! - S: sensor number, 0 for 1R (refrigerator) and 1 for 2F (freezer)
+//! - C: CRC-8 of the previous bytes, computed in the order the bits are sent
+
+use crate::{
+    crc8le,
+    pulse::{self, Encoding, Pulse, Pulses},
+    DeciCelsius,
+};
+
+/// Timing of the radio pulses. Each message starts with four long sync pulses,
+/// and bits are distinguished by the length of the gap following each pulse.
+pub const ENCODING: Encoding = Encoding {
+    sync: Pulse::new(1600, 1600),
+    sync_count: 4,
+    zero: Pulse::new(400, 520),
+    one: Pulse::new(400, 880),
+    stop: Some(Pulse::new(400, 400)),
+};
+
+/// Number of times each message is sent
+pub const REPEATS: usize = 2;
+
+/// Lowest temperature that can be sent, in degrees Fahrenheit
+pub const TEMPERATURE_MIN: i16 = -0x7f;
+/// Highest temperature that can be sent, in degrees Fahrenheit
+pub const TEMPERATURE_MAX: i16 = 0x7f;
+
+const CRC_POLY: u8 = 0x07;
+
+const STATUS_SENSOR: u8 = 1 << 0;
+const STATUS_BATTERY_LOW: u8 = 1 << 1;
+const STATUS_RESERVED: u8 = !(STATUS_SENSOR | STATUS_BATTERY_LOW);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    TemperatureRange(i16),
+    /// The CRC byte does not match the CRC computed from the rest of the
+    /// message.
+    Crc {
+        expected: u8,
+        actual: u8,
+    },
+    /// One of the status bits that are always zero in genuine messages was
+    /// set.
+    ReservedBits(u8),
+}
+
+/// Which of the two sensors paired with a display sent the message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sensor {
+    /// Sensor 1R
+    Refrigerator,
+    /// Sensor 2F
+    Freezer,
+}
+
+/// A single 986 message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Message {
+    sensor: Sensor,
+    id: u16,
+    battery_ok: bool,
+    temperature: i16,
+}
+
+impl Message {
+    /// Create a message with a temperature in degrees Fahrenheit. Fails if the
+    /// temperature is outside of the range -127 to 127 °F.
+    pub const fn new(
+        sensor: Sensor,
+        id: u16,
+        battery_ok: bool,
+        temperature: i16,
+    ) -> Result<Self, Error> {
+        if temperature < TEMPERATURE_MIN || temperature > TEMPERATURE_MAX {
+            return Err(Error::TemperatureRange(temperature));
+        }
+        Ok(Self {
+            sensor,
+            id,
+            battery_ok,
+            temperature,
+        })
+    }
+
+    /// Create a message from a temperature in Celsius, which is rounded to the
+    /// nearest degree Fahrenheit.
+    pub const fn from_celsius(
+        sensor: Sensor,
+        id: u16,
+        battery_ok: bool,
+        temperature: DeciCelsius,
+    ) -> Result<Self, Error> {
+        Self::new(sensor, id, battery_ok, temperature.to_fahrenheit())
+    }
+
+    pub const fn sensor(&self) -> Sensor {
+        self.sensor
+    }
+
+    pub const fn id(&self) -> u16 {
+        self.id
+    }
+
+    pub const fn battery_ok(&self) -> bool {
+        self.battery_ok
+    }
+
+    /// Temperature in degrees Fahrenheit
+    pub const fn temperature(&self) -> i16 {
+        self.temperature
+    }
+
+    pub const fn temperature_celsius(&self) -> DeciCelsius {
+        DeciCelsius::from_deci_fahrenheit(self.temperature * 10)
+    }
+
+    /// Message bytes, in the order they are sent but with the bits of each
+    /// byte in normal (MSB first) order.
+    pub fn to_bytes(&self) -> [u8; 5] {
+        let temperature = if self.temperature < 0 {
+            0x80 | (-self.temperature) as u8
+        } else {
+            self.temperature as u8
+        };
+        let mut status = 0;
+        if self.sensor == Sensor::Freezer {
+            status |= STATUS_SENSOR;
+        }
+        if !self.battery_ok {
+            status |= STATUS_BATTERY_LOW;
+        }
+        let [id_high, id_low] = self.id.to_be_bytes();
+        let mut bytes = [temperature, id_high, id_low, status, 0];
+        // The CRC is computed over the bytes as sent, so it is reflected here
+        bytes[4] = crc8le(&bytes[..4], CRC_POLY, 0);
+        bytes
+    }
+
+    /// Decode a received message, verifying its CRC. The bits of each byte
+    /// must already be in normal (MSB first) order.
+    pub fn from_bytes(bytes: [u8; 5]) -> Result<Self, Error> {
+        let expected = crc8le(&bytes[..4], CRC_POLY, 0);
+        if bytes[4] != expected {
+            return Err(Error::Crc {
+                expected,
+                actual: bytes[4],
+            });
+        }
+
+        let status = bytes[3];
+        if status & STATUS_RESERVED != 0 {
+            return Err(Error::ReservedBits(status & STATUS_RESERVED));
+        }
+
+        let magnitude = (bytes[0] & 0x7f) as i16;
+        Self::new(
+            if status & STATUS_SENSOR != 0 {
+                Sensor::Freezer
+            } else {
+                Sensor::Refrigerator
+            },
+            u16::from_be_bytes([bytes[1], bytes[2]]),
+            status & STATUS_BATTERY_LOW == 0,
+            if bytes[0] & 0x80 != 0 {
+                -magnitude
+            } else {
+                magnitude
+            },
+        )
+    }
+
+    /// Radio pulses to transmit this message
+    pub fn pulses(&self) -> Pulses<5> {
+        ENCODING.encode(self.to_bytes().map(u8::reverse_bits))
+    }
+}
+
+/// Receives 986 messages from radio pulses
+pub type Receiver = pulse::Receiver<fn([u8; 5]) -> Result<Message, Error>, 5>;
+
+/// Create a receiver that accepts pulses within `tolerance_pct` percent of the
+/// nominal timing.
+pub const fn receiver(tolerance_pct: u8) -> Receiver {
+    Receiver::new(ENCODING, tolerance_pct, decode_received)
+}
+
+/// Decode a message with the bits of each byte in the order they are received
+fn decode_received(bytes: [u8; 5]) -> Result<Message, Error> {
+    Message::from_bytes(bytes.map(u8::reverse_bits))
+}
@@ -0,0 +1,282 @@
+use std::ops::Range;
+
+use acurite_protocol::{pulse::Pulse, DeciCelsius};
+use acurite_thermometer_core::{
+    compensation::{Conditions, Table},
+    sleep::{self, Calibration, Schedule, Timeout},
+    tmp102, Board,
+};
+
+/// Simulated time in nanoseconds
+pub type Nanos = u64;
+
+pub const NANOS_PER_US: Nanos = 1_000;
+pub const NANOS_PER_MS: Nanos = 1_000_000;
+pub const NANOS_PER_S: Nanos = 1_000_000_000;
+
+/// CPU frequency assumed by the firmware
+pub const CPU_FREQUENCY_HZ: u32 = 1_000_000;
+
+/// Temperature at which the watchdog frequency error is specified
+pub const REFERENCE_TEMPERATURE: f64 = 25.0;
+
+/// Watchdog clocked by an oscillator that deviates from its nominal frequency
+/// and drifts with temperature
+#[derive(Debug, Clone, Copy)]
+pub struct Watchdog {
+    frequency_hz: f64,
+    /// Change in frequency per degree from the reference temperature, in parts
+    /// per million
+    tempco_ppm: f64,
+}
+
+impl Watchdog {
+    /// Create a watchdog whose oscillator is off by `error_pct` percent at the
+    /// reference temperature and changes by `tempco_ppm` per degree.
+    pub fn new(error_pct: f64, tempco_ppm: f64) -> Self {
+        Self {
+            frequency_hz: sleep::WDT_FREQUENCY_HZ as f64 * (1.0 + error_pct / 100.0),
+            tempco_ppm,
+        }
+    }
+
+    /// Frequency at the reference temperature
+    pub fn frequency_hz(&self) -> f64 {
+        self.frequency_hz
+    }
+
+    pub fn frequency_hz_at(&self, temperature: f64) -> f64 {
+        self.frequency_hz * (1.0 + (temperature - REFERENCE_TEMPERATURE) * self.tempco_ppm / 1e6)
+    }
+
+    /// Simulated time until a timeout expires at `temperature`
+    pub fn duration(&self, timeout: Timeout, temperature: f64) -> Nanos {
+        (timeout.cycles() as f64 * NANOS_PER_S as f64 / self.frequency_hz_at(temperature)).round()
+            as Nanos
+    }
+}
+
+/// CPU clock used to calibrate the watchdog, which may also deviate from its
+/// nominal frequency
+#[derive(Debug, Clone, Copy)]
+pub struct CpuClock {
+    frequency_hz: f64,
+}
+
+impl CpuClock {
+    /// Create a CPU clock that is off by `error_pct` percent.
+    pub fn new(error_pct: f64) -> Self {
+        Self {
+            frequency_hz: CPU_FREQUENCY_HZ as f64 * (1.0 + error_pct / 100.0),
+        }
+    }
+
+    /// Number of CPU cycles counted during `duration`
+    pub fn cycles(&self, duration: Nanos) -> u32 {
+        (duration as f64 * self.frequency_hz / NANOS_PER_S as f64).round() as u32
+    }
+}
+
+/// Simulated TMP102 that always measures the same temperature
+#[derive(Debug, Clone, Copy)]
+pub struct Tmp102 {
+    /// Temperature in thousandths of a degree, or `None` if the sensor does not
+    /// respond
+    pub millidegrees: Option<i32>,
+}
+
+/// The simulated sensor did not respond
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SensorError;
+
+impl Tmp102 {
+    /// Time the firmware waits for a one-shot conversion
+    pub const CONVERSION_TIME: Nanos = 30 * NANOS_PER_MS;
+
+    /// Temperature register value in extended mode, rounded to the 0.0625 °C
+    /// resolution of the sensor.
+    pub fn oneshot(&self) -> Result<i16, SensorError> {
+        let millidegrees = self.millidegrees.ok_or(SensorError)?;
+        let counts = (millidegrees as f64 * 16.0 / 1000.0).round() as i32;
+        let counts = counts.clamp(-(1 << 12), (1 << 12) - 1);
+        Ok(((counts << 3) | 1) as i16)
+    }
+}
+
+/// Level change of the radio data pin
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Edge {
+    pub time: Nanos,
+    pub high: bool,
+}
+
+/// Edges produced by a single call to [`Board::transmit`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transmission {
+    /// Indices into [`SimBoard::edges`]
+    pub edges: Range<usize>,
+    /// Time when the final space ended
+    pub end: Nanos,
+}
+
+/// Board that advances simulated time instead of waiting
+pub struct SimBoard {
+    pub sensor: Tmp102,
+    pub battery_mv: u16,
+    pub watchdog: Watchdog,
+    pub cpu_clock: CpuClock,
+    pub calibration: Calibration,
+    /// Compensation table, which the firmware keeps in EEPROM
+    pub table: Table,
+    /// Record calibrations in the compensation table
+    pub calibration_mode: bool,
+    /// Schedule of the current sleep period
+    pub schedule: Schedule,
+    /// Schedule of the next sleep period, planned before going to sleep
+    next_schedule: Schedule,
+    /// Conditions measured during the last cycle
+    conditions: Option<Conditions>,
+    /// Current simulated time
+    pub now: Nanos,
+    /// Time the first watchdog period of the sleep sequence was started
+    watchdog_start: Nanos,
+    /// Number of times the first watchdog period expired before the cycle
+    /// finished, which would reset the MCU
+    pub overruns: usize,
+    pub edges: Vec<Edge>,
+    pub transmissions: Vec<Transmission>,
+}
+
+impl SimBoard {
+    pub fn new(
+        sensor: Tmp102,
+        battery_mv: u16,
+        watchdog: Watchdog,
+        cpu_clock: CpuClock,
+        table: Table,
+        calibration_mode: bool,
+    ) -> Self {
+        // Calibrate every cycle in calibration mode
+        let interval = if calibration_mode {
+            1
+        } else {
+            sleep::CALIBRATION_INTERVAL
+        };
+        Self {
+            sensor,
+            battery_mv,
+            watchdog,
+            cpu_clock,
+            calibration: Calibration::new(interval),
+            table,
+            calibration_mode,
+            schedule: Schedule::new(0),
+            next_schedule: Schedule::new(0),
+            conditions: None,
+            now: 0,
+            watchdog_start: 0,
+            overruns: 0,
+            edges: Vec::new(),
+            transmissions: Vec::new(),
+        }
+    }
+
+    /// Start the first watchdog period of the next sleep, calibrating the
+    /// watchdog first if necessary. The firmware also does this before entering
+    /// the main loop.
+    pub fn start_watchdog(&mut self) {
+        if self.calibration.due() {
+            // Wait for one timeout to synchronize, then count CPU cycles during
+            // the next
+            let duration = self.watchdog_duration(sleep::CALIBRATION_TIMEOUT);
+            self.now += 2 * duration;
+            let updated = self.calibration.update(
+                self.cpu_clock.cycles(duration),
+                CPU_FREQUENCY_HZ,
+                self.conditions,
+            );
+            if updated && self.calibration_mode {
+                self.calibration.record(&mut self.table);
+            }
+            self.schedule = self.calibration.plan(self.conditions, &self.table);
+        } else {
+            self.schedule = self.next_schedule;
+        }
+        self.watchdog_start = self.now;
+    }
+
+    /// Duration of a watchdog timeout at the current temperature
+    fn watchdog_duration(&self, timeout: Timeout) -> Nanos {
+        let temperature = self
+            .sensor
+            .millidegrees
+            .map_or(REFERENCE_TEMPERATURE, |t| t as f64 / 1000.0);
+        self.watchdog.duration(timeout, temperature)
+    }
+
+    fn set_radio(&mut self, high: bool) {
+        self.edges.push(Edge {
+            time: self.now,
+            high,
+        });
+    }
+}
+
+impl Board for SimBoard {
+    type SensorError = SensorError;
+
+    fn read_temperature(&mut self) -> Result<DeciCelsius, SensorError> {
+        self.now += Tmp102::CONVERSION_TIME;
+        let temperature = self
+            .sensor
+            .oneshot()
+            .map(|register| tmp102::Reading::from_register(register).temperature());
+        self.conditions = temperature.ok().map(|temperature| Conditions {
+            temperature,
+            battery_mv: self.battery_mv,
+        });
+        temperature
+    }
+
+    fn read_battery_mv(&mut self) -> u16 {
+        self.battery_mv
+    }
+
+    fn transmit(&mut self, pulses: impl Iterator<Item = Pulse>) {
+        let start = self.edges.len();
+        for pulse in pulses {
+            self.set_radio(true);
+            self.now += pulse.mark_us as Nanos * NANOS_PER_US;
+            self.set_radio(false);
+            self.now += pulse.space_us as Nanos * NANOS_PER_US;
+        }
+        self.transmissions.push(Transmission {
+            edges: start..self.edges.len(),
+            end: self.now,
+        });
+    }
+
+    fn set_led(&mut self, _on: bool) {}
+
+    fn delay_ms(&mut self, ms: u16) {
+        self.now += ms as Nanos * NANOS_PER_MS;
+    }
+
+    fn sleep(&mut self) {
+        self.next_schedule = self.calibration.next_schedule(self.conditions, &self.table);
+
+        let schedule = self.schedule;
+        let timeouts = schedule.timeouts();
+        // The first period has been running since the end of the last sleep
+        let wake = self.watchdog_start + self.watchdog_duration(timeouts[0]);
+        if self.now > wake {
+            self.overruns += 1;
+        }
+        self.now = self.now.max(wake);
+
+        for timeout in &timeouts[1..] {
+            self.now += self.watchdog_duration(*timeout);
+        }
+        self.start_watchdog();
+    }
+}
@@ -0,0 +1,265 @@
+//! Runs the firmware main loop on the host against simulated hardware, to check
+//! the transmit timing and waveform without flashing a board.
+
+use std::{env, process, str::FromStr};
+
+use acurite_protocol::{tx592, tx986};
+use acurite_thermometer_core::{compensation::Table, config::Config, protocol, sleep};
+
+use board::{CpuClock, SimBoard, Tmp102, Watchdog, NANOS_PER_MS, NANOS_PER_S};
+use sim::{Cycle, Kind, Receiver};
+
+mod board;
+mod sim;
+#[cfg(test)]
+mod test;
+
+const USAGE: &str = "\
+Usage: acurite-simulator [OPTIONS]
+
+Options:
+    --protocol <NAME>        tx00606, tx592, tx609 or tx986 [default: tx00606]
+    --cycles <N>             Number of cycles to simulate [default: 10]
+    --wdt-error <PERCENT>    Watchdog oscillator frequency error [default: 0]
+    --wdt-tempco <PPM>       Watchdog frequency change per degree [default: 0]
+    --cpu-error <PERCENT>    CPU clock frequency error [default: 0]
+    --calibration-mode       Calibrate every cycle and fill the compensation table
+    --temperature <CELSIUS>  Temperature measured by the TMP102 [default: 21.5]
+    --sensor-error           TMP102 does not respond
+    --battery <MV>           Battery voltage [default: 3000]
+    --id <ID>                Transmitter ID [default: 90]
+    --window <MS>            Width of the display's listen window [default: 500]
+    --tolerance <PERCENT>    Receiver pulse timing tolerance [default: 10]
+    --edges                  Print every radio pin edge
+";
+
+#[derive(Clone, Copy)]
+struct Options {
+    kind: Kind,
+    cycles: usize,
+    wdt_error_pct: f64,
+    wdt_tempco_ppm: f64,
+    cpu_error_pct: f64,
+    calibration_mode: bool,
+    temperature: Option<f64>,
+    battery_mv: u16,
+    id: u8,
+    window_ms: u32,
+    tolerance_pct: u8,
+    edges: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            kind: Kind::Tx00606,
+            cycles: 10,
+            wdt_error_pct: 0.0,
+            wdt_tempco_ppm: 0.0,
+            cpu_error_pct: 0.0,
+            calibration_mode: false,
+            temperature: Some(21.5),
+            battery_mv: 3000,
+            id: 90,
+            window_ms: 500,
+            tolerance_pct: 10,
+            edges: false,
+        }
+    }
+}
+
+fn parse_value<T: FromStr>(name: &str, value: Option<String>) -> Result<T, String> {
+    let value = value.ok_or_else(|| format!("missing value for {name}"))?;
+    value
+        .parse()
+        .map_err(|_| format!("invalid value for {name}: {value}"))
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options::default();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--protocol" => options.kind = parse_value(&arg, args.next())?,
+            "--cycles" => options.cycles = parse_value(&arg, args.next())?,
+            "--wdt-error" => options.wdt_error_pct = parse_value(&arg, args.next())?,
+            "--wdt-tempco" => options.wdt_tempco_ppm = parse_value(&arg, args.next())?,
+            "--cpu-error" => options.cpu_error_pct = parse_value(&arg, args.next())?,
+            "--calibration-mode" => options.calibration_mode = true,
+            "--temperature" => options.temperature = Some(parse_value(&arg, args.next())?),
+            "--sensor-error" => options.temperature = None,
+            "--battery" => options.battery_mv = parse_value(&arg, args.next())?,
+            "--id" => options.id = parse_value(&arg, args.next())?,
+            "--window" => options.window_ms = parse_value(&arg, args.next())?,
+            "--tolerance" => options.tolerance_pct = parse_value(&arg, args.next())?,
+            "--edges" => options.edges = true,
+            "--help" | "-h" => {
+                print!("{USAGE}");
+                process::exit(0);
+            }
+            _ => return Err(format!("unknown argument: {arg}")),
+        }
+    }
+    Ok(options)
+}
+
+fn seconds(time: u64) -> f64 {
+    time as f64 / NANOS_PER_S as f64
+}
+
+fn millis(time: i64) -> f64 {
+    time as f64 / NANOS_PER_MS as f64
+}
+
+/// Boot the simulated board, using `table` for temperature and voltage
+/// compensation
+fn boot(options: &Options, table: Table) -> SimBoard {
+    let sensor = Tmp102 {
+        millidegrees: options.temperature.map(|t| (t * 1000.0).round() as i32),
+    };
+    let mut board = SimBoard::new(
+        sensor,
+        options.battery_mv,
+        Watchdog::new(options.wdt_error_pct, options.wdt_tempco_ppm),
+        CpuClock::new(options.cpu_error_pct),
+        table,
+        options.calibration_mode,
+    );
+    board.start_watchdog();
+    board
+}
+
+fn run(options: &Options) -> (SimBoard, Vec<Cycle>) {
+    let mut board = boot(options, Table::new());
+    let cycles = simulate(&mut board, options);
+    (board, cycles)
+}
+
+/// Run the main loop on a booted board
+fn simulate(board: &mut SimBoard, options: &Options) -> Vec<Cycle> {
+    let config = Config {
+        id: options.id,
+        ..Config::DEFAULT
+    };
+    let id = config.id;
+    let config = config.cycle();
+    match options.kind {
+        Kind::Tx00606 => sim::simulate(
+            board,
+            &mut protocol::Tx00606 { id },
+            &config,
+            options.cycles,
+        ),
+        Kind::Tx592 => sim::simulate(
+            board,
+            &mut protocol::Tx592::new(tx592::Channel::A, id.into(), 0),
+            &config,
+            options.cycles,
+        ),
+        Kind::Tx609 => sim::simulate(
+            board,
+            &mut protocol::Tx609 { id, humidity: 0 },
+            &config,
+            options.cycles,
+        ),
+        Kind::Tx986 => sim::simulate(
+            board,
+            &mut protocol::Tx986 {
+                sensor: tx986::Sensor::Freezer,
+                id: id.into(),
+            },
+            &config,
+            options.cycles,
+        ),
+    }
+}
+
+fn main() {
+    let options = match parse_args(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("error: {e}\n\n{USAGE}");
+            process::exit(2);
+        }
+    };
+
+    let (board, cycles) = run(&options);
+    let timing = sim::timing(&cycles);
+    let mut receiver = Receiver::new(options.kind, options.tolerance_pct);
+    let window = options.window_ms as i64 * NANOS_PER_MS as i64;
+
+    println!(
+        "Watchdog at {:.1} Hz, calibrated to {} Hz, display period {:.3} s",
+        board.watchdog.frequency_hz(),
+        board.calibration.frequency_hz(),
+        sleep::PERIOD_MS as f64 / 1000.0,
+    );
+
+    let mut missed = 0;
+    let mut decoded = 0;
+    for (i, (cycle, timing)) in cycles.iter().zip(&timing).enumerate() {
+        print!("cycle {i}: ");
+        match cycle.start {
+            Some(start) => print!("start {:.3} s", seconds(start)),
+            None => print!("no transmission"),
+        }
+        if let Some(timing) = timing {
+            let outside = timing.error.abs() > window / 2;
+            missed += outside as usize;
+            print!(
+                ", period {:.3} s, error {:+.3} ms, drift {:+.3} ms{}",
+                seconds(timing.period),
+                millis(timing.error),
+                millis(timing.drift),
+                if outside { " (outside window)" } else { "" }
+            );
+        }
+        println!();
+        if let Some(error) = &cycle.error {
+            println!("    error: {error}");
+        }
+
+        for frame in sim::decode(&board, cycle, &mut receiver) {
+            decoded += frame.is_ok() as usize;
+            println!("    frame: {frame:?}");
+        }
+
+        if options.edges {
+            for transmission in &board.transmissions[cycle.transmissions.clone()] {
+                for edge in &board.edges[transmission.edges.clone()] {
+                    println!(
+                        "    edge: {:.6} s {}",
+                        seconds(edge.time),
+                        if edge.high { "high" } else { "low" }
+                    );
+                }
+            }
+        }
+    }
+
+    println!("{decoded} frames decoded without errors");
+    let periods: Vec<_> = timing.iter().flatten().map(|t| t.period).collect();
+    if !periods.is_empty() {
+        let mean = periods.iter().sum::<u64>() / periods.len() as u64;
+        println!(
+            "period: min {:.3} s, mean {:.3} s, max {:.3} s",
+            seconds(*periods.iter().min().unwrap()),
+            seconds(mean),
+            seconds(*periods.iter().max().unwrap()),
+        );
+        let error = mean as i64 - sleep::PERIOD_MS as i64 * NANOS_PER_MS as i64;
+        println!(
+            "mean error {:+.3} ms per cycle ({:+.0} ppm), {} of {} cycles outside the {} ms window",
+            millis(error),
+            error as f64 * 1e6 / (sleep::PERIOD_MS as f64 * NANOS_PER_MS as f64),
+            missed,
+            periods.len(),
+            options.window_ms,
+        );
+    }
+    if board.overruns > 0 {
+        println!(
+            "warning: {} cycles took longer than the first watchdog period, which would reset the MCU",
+            board.overruns
+        );
+    }
+}
@@ -0,0 +1,185 @@
+use std::{fmt::Debug, ops::Range, str::FromStr};
+
+use acurite_protocol::{pulse::Pulse, tx00606, tx592, tx609, tx986};
+use acurite_thermometer_core::{run_cycle, sleep, CycleConfig, Protocol};
+
+use crate::board::{Edge, Nanos, SimBoard, NANOS_PER_MS, NANOS_PER_US};
+
+/// Protocol emulated by the firmware
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Tx00606,
+    Tx592,
+    Tx609,
+    Tx986,
+}
+
+impl FromStr for Kind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "tx00606" => Ok(Self::Tx00606),
+            "tx592" => Ok(Self::Tx592),
+            "tx609" => Ok(Self::Tx609),
+            "tx986" => Ok(Self::Tx986),
+            _ => Err(format!("unknown protocol: {s}")),
+        }
+    }
+}
+
+/// Frame decoded from the recorded radio waveform
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Frame {
+    Tx00606(Result<tx00606::Message, tx00606::Error>),
+    Tx592(Result<tx592::Message, tx592::Error>),
+    Tx609(Result<tx609::Message, tx609::Error>),
+    Tx986(Result<tx986::Message, tx986::Error>),
+}
+
+impl Frame {
+    pub fn is_ok(&self) -> bool {
+        match self {
+            Self::Tx00606(r) => r.is_ok(),
+            Self::Tx592(r) => r.is_ok(),
+            Self::Tx609(r) => r.is_ok(),
+            Self::Tx986(r) => r.is_ok(),
+        }
+    }
+}
+
+/// Receiver for any of the supported protocols
+pub enum Receiver {
+    Tx00606(tx00606::Receiver),
+    Tx592(tx592::Receiver),
+    Tx609(tx609::Receiver),
+    Tx986(tx986::Receiver),
+}
+
+impl Receiver {
+    pub fn new(kind: Kind, tolerance_pct: u8) -> Self {
+        match kind {
+            Kind::Tx00606 => Self::Tx00606(tx00606::receiver(tolerance_pct)),
+            Kind::Tx592 => Self::Tx592(tx592::receiver(tolerance_pct)),
+            Kind::Tx609 => Self::Tx609(tx609::receiver(tolerance_pct)),
+            Kind::Tx986 => Self::Tx986(tx986::receiver(tolerance_pct)),
+        }
+    }
+
+    pub fn feed(&mut self, pulse: Pulse) -> Option<Frame> {
+        match self {
+            Self::Tx00606(r) => r.feed(pulse).map(Frame::Tx00606),
+            Self::Tx592(r) => r.feed(pulse).map(Frame::Tx592),
+            Self::Tx609(r) => r.feed(pulse).map(Frame::Tx609),
+            Self::Tx986(r) => r.feed(pulse).map(Frame::Tx986),
+        }
+    }
+}
+
+fn to_us(duration: Nanos) -> u16 {
+    ((duration + NANOS_PER_US / 2) / NANOS_PER_US).min(u16::MAX as Nanos) as u16
+}
+
+/// Convert a recorded waveform back into pulses. The final space lasts until
+/// `end`.
+pub fn pulses(edges: &[Edge], end: Nanos) -> Vec<Pulse> {
+    let rising: Vec<&Edge> = edges.iter().filter(|e| e.high).collect();
+    let falling: Vec<&Edge> = edges.iter().filter(|e| !e.high).collect();
+    rising
+        .iter()
+        .zip(&falling)
+        .enumerate()
+        .map(|(i, (rise, fall))| {
+            let next = rising.get(i + 1).map_or(end, |e| e.time);
+            Pulse {
+                mark_us: to_us(fall.time - rise.time),
+                space_us: to_us(next - fall.time),
+            }
+        })
+        .collect()
+}
+
+/// Result of a single firmware cycle
+#[derive(Debug, Clone)]
+pub struct Cycle {
+    /// Time of the first rising edge, if anything was transmitted
+    pub start: Option<Nanos>,
+    /// Indices into [`SimBoard::transmissions`]
+    pub transmissions: Range<usize>,
+    /// Error reported by [`run_cycle`], if any
+    pub error: Option<String>,
+}
+
+/// Run the firmware main loop for `count` cycles.
+pub fn simulate<P>(
+    board: &mut SimBoard,
+    protocol: &mut P,
+    config: &CycleConfig,
+    count: usize,
+) -> Vec<Cycle>
+where
+    P: Protocol,
+    P::Error: Debug,
+{
+    (0..count)
+        .map(|_| {
+            let first_edge = board.edges.len();
+            let first_transmission = board.transmissions.len();
+            let result = run_cycle(board, protocol, config);
+            Cycle {
+                start: board.edges.get(first_edge).map(|e| e.time),
+                transmissions: first_transmission..board.transmissions.len(),
+                error: result.err().map(|e| format!("{e:?}")),
+            }
+        })
+        .collect()
+}
+
+/// Decode every frame transmitted during a cycle
+pub fn decode(board: &SimBoard, cycle: &Cycle, receiver: &mut Receiver) -> Vec<Frame> {
+    board.transmissions[cycle.transmissions.clone()]
+        .iter()
+        .flat_map(|t| pulses(&board.edges[t.edges.clone()], t.end))
+        .filter_map(|p| receiver.feed(p))
+        .collect()
+}
+
+/// Timing of a cycle relative to the display
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timing {
+    /// Time between cycles since the previous transmission
+    pub period: Nanos,
+    /// Difference between the period and the interval expected by the
+    /// display
+    pub error: i64,
+    /// Accumulated difference from the schedule of the first transmission
+    pub drift: i64,
+}
+
+/// Compute the timing of each cycle that transmitted after the first one.
+/// Cycles that failed to transmit are skipped, assuming the display keeps
+/// listening on its original schedule.
+pub fn timing(cycles: &[Cycle]) -> Vec<Option<Timing>> {
+    let expected = sleep::PERIOD_MS as i64 * NANOS_PER_MS as i64;
+    let first = cycles.iter().position(|c| c.start.is_some());
+    let mut previous: Option<(usize, Nanos)> = None;
+    cycles
+        .iter()
+        .enumerate()
+        .map(|(i, cycle)| {
+            let start = cycle.start?;
+            let timing = previous.map(|(j, previous_start)| {
+                let period = (start - previous_start) / (i - j) as Nanos;
+                let first = first.unwrap();
+                Timing {
+                    period,
+                    error: period as i64 - expected,
+                    drift: (start - cycles[first].start.unwrap()) as i64
+                        - (i - first) as i64 * expected,
+                }
+            });
+            previous = Some((i, start));
+            timing
+        })
+        .collect()
+}
@@ -0,0 +1,244 @@
+use acurite_protocol::{tx00606, tx592, tx609, tx986, DeciCelsius};
+use acurite_thermometer_core::{compensation::Table, sleep};
+
+use super::*;
+
+fn options(kind: Kind) -> Options {
+    Options {
+        kind,
+        ..Default::default()
+    }
+}
+
+fn frames(options: &Options) -> Vec<Vec<sim::Frame>> {
+    let (board, cycles) = run(options);
+    let mut receiver = Receiver::new(options.kind, options.tolerance_pct);
+    cycles
+        .iter()
+        .map(|c| sim::decode(&board, c, &mut receiver))
+        .collect()
+}
+
+#[test]
+fn test_tmp102_resolution() {
+    let sensor = Tmp102 {
+        millidegrees: Some(21_530),
+    };
+    // 21.53 °C rounds to 21.5 °C at 0.0625 °C resolution
+    assert_eq!(
+        DeciCelsius::new(215),
+        DeciCelsius::from_tmp102_extended(sensor.oneshot().unwrap())
+    );
+}
+
+/// Check that every period is within half of the shortest timeout of the
+/// display period, plus the resolution of the calibration
+fn assert_calibrated(options: &Options) {
+    let (board, cycles) = run(options);
+    let tolerance = (sleep::Timeout::Ms16.cycles() as f64 / 2.0 / board.watchdog.frequency_hz()
+        * NANOS_PER_S as f64) as i64
+        + NANOS_PER_MS as i64;
+    let timing = sim::timing(&cycles);
+    assert_eq!(None, timing[0]);
+    for timing in timing.into_iter().skip(1) {
+        let error = timing.unwrap().error;
+        assert!(error.abs() <= tolerance, "error: {error}");
+    }
+    assert_eq!(0, board.overruns);
+}
+
+#[test]
+fn test_calibrated_period() {
+    for wdt_error_pct in [-20.0, -5.0, 0.0, 4.08, 10.0, 20.0] {
+        assert_calibrated(&Options {
+            wdt_error_pct,
+            cycles: 2 * sleep::CALIBRATION_INTERVAL as usize + 1,
+            ..options(Kind::Tx00606)
+        });
+    }
+}
+
+#[test]
+fn test_cpu_clock_error() {
+    // The period is only as accurate as the CPU clock
+    let options = Options {
+        wdt_error_pct: 4.08,
+        cpu_error_pct: 1.0,
+        ..options(Kind::Tx00606)
+    };
+    let (_, cycles) = run(&options);
+    for timing in sim::timing(&cycles).into_iter().skip(1) {
+        let error = timing.unwrap().error;
+        // A fast CPU clock shortens the period by 1% of 31 s, within half of the
+        // shortest timeout
+        assert!((error + 310 * NANOS_PER_MS as i64).abs() < 10 * NANOS_PER_MS as i64);
+    }
+}
+
+#[test]
+fn test_drift() {
+    let (_, cycles) = run(&options(Kind::Tx00606));
+    let mut drift = 0;
+    for timing in sim::timing(&cycles).into_iter().skip(1) {
+        let timing = timing.unwrap();
+        drift += timing.error;
+        assert_eq!(drift, timing.drift);
+    }
+}
+
+#[test]
+fn test_decode_00606tx() {
+    let expected = tx00606::Message::new(90, true, DeciCelsius::new(215)).unwrap();
+    for frames in frames(&options(Kind::Tx00606)) {
+        assert_eq!(
+            vec![sim::Frame::Tx00606(Ok(expected)); tx00606::REPEATS],
+            frames
+        );
+    }
+}
+
+#[test]
+fn test_decode_592txr() {
+    for (i, frames) in frames(&options(Kind::Tx592)).into_iter().enumerate() {
+        let expected = tx592::Message::new(
+            tx592::Channel::A,
+            i as u8 & tx592::SEQUENCE_MAX,
+            90,
+            true,
+            0,
+            DeciCelsius::new(215),
+        )
+        .unwrap();
+        assert_eq!(
+            vec![sim::Frame::Tx592(Ok(expected)); tx592::REPEATS],
+            frames
+        );
+    }
+}
+
+#[test]
+fn test_decode_609txc() {
+    let expected = tx609::Message::new(90, true, 0, DeciCelsius::new(215)).unwrap();
+    for frames in frames(&options(Kind::Tx609)) {
+        assert_eq!(
+            vec![sim::Frame::Tx609(Ok(expected)); tx609::REPEATS],
+            frames
+        );
+    }
+}
+
+#[test]
+fn test_decode_986() {
+    let expected =
+        tx986::Message::from_celsius(tx986::Sensor::Freezer, 90, true, DeciCelsius::new(215))
+            .unwrap();
+    for frames in frames(&options(Kind::Tx986)) {
+        assert_eq!(
+            vec![sim::Frame::Tx986(Ok(expected)); tx986::REPEATS],
+            frames
+        );
+    }
+}
+
+#[test]
+fn test_low_battery() {
+    let options = Options {
+        battery_mv: 1900,
+        ..options(Kind::Tx00606)
+    };
+    let expected = tx00606::Message::new(90, false, DeciCelsius::new(215)).unwrap();
+    assert!(frames(&options)
+        .iter()
+        .flatten()
+        .all(|f| *f == sim::Frame::Tx00606(Ok(expected))));
+}
+
+#[test]
+fn test_sensor_error() {
+    let options = Options {
+        temperature: None,
+        ..options(Kind::Tx00606)
+    };
+    let (board, cycles) = run(&options);
+    assert!(board.edges.is_empty());
+    for cycle in &cycles {
+        assert_eq!(None, cycle.start);
+        assert!(cycle.error.is_some());
+    }
+    assert!(sim::timing(&cycles).iter().all(Option::is_none));
+}
+
+#[test]
+fn test_pulses() {
+    let (board, _) = run(&options(Kind::Tx00606));
+    let expected: Vec<_> = tx00606::Message::new(90, true, DeciCelsius::new(215))
+        .unwrap()
+        .pulses()
+        .collect();
+    for transmission in &board.transmissions {
+        assert_eq!(
+            expected,
+            sim::pulses(&board.edges[transmission.edges.clone()], transmission.end)
+        );
+    }
+}
+
+#[test]
+fn test_parse_args() {
+    let args = [
+        "--protocol",
+        "tx592",
+        "--cycles",
+        "3",
+        "--wdt-error",
+        "-1.5",
+    ];
+    let options = parse_args(args.iter().map(|s| s.to_string())).unwrap();
+    assert_eq!(Kind::Tx592, options.kind);
+    assert_eq!(3, options.cycles);
+    assert_eq!(-1.5, options.wdt_error_pct);
+
+    assert!(parse_args(["--cycles"].iter().map(|s| s.to_string())).is_err());
+    assert!(parse_args(["--protocol", "x"].iter().map(|s| s.to_string())).is_err());
+}
+
+#[test]
+fn test_temperature_compensation() {
+    // Watchdog runs 2% slower at -20 °C than at +20 °C
+    let warm = Options {
+        wdt_tempco_ppm: 500.0,
+        temperature: Some(20.0),
+        cycles: 3,
+        ..options(Kind::Tx00606)
+    };
+    let cold = Options {
+        temperature: Some(-20.0),
+        ..warm
+    };
+
+    // Fill the table in calibration mode
+    let mut table = Table::new();
+    for options in [warm, cold] {
+        let options = Options {
+            calibration_mode: true,
+            ..options
+        };
+        let mut board = boot(&options, table);
+        simulate(&mut board, &options);
+        table = board.table;
+    }
+
+    // Period error after calibrating at +20 °C and moving outside
+    let error = |table| {
+        let mut board = boot(&warm, table);
+        simulate(&mut board, &warm);
+        board.sensor = Tmp102 {
+            millidegrees: Some(-20_000),
+        };
+        let cycles = simulate(&mut board, &warm);
+        // The first period after moving was planned for the old temperature
+        sim::timing(&cycles)[2].unwrap().error
+    };
+    assert!(error(Table::new()) > 500 * NANOS_PER_MS as i64);
+    assert!(error(table).abs() < 10 * NANOS_PER_MS as i64);
+}
@@ -0,0 +1,243 @@
+//! Correction of the watchdog frequency for temperature and supply voltage
+//!
+//! The watchdog oscillator frequency shifts with temperature and voltage. The
+//! frequency measured at a grid of conditions is stored in a table, which is
+//! used to correct the last calibration when the conditions have changed since.
+
+use acurite_protocol::{crc8, DeciCelsius};
+
+/// Temperature of the first table column
+pub const TEMPERATURE_MIN: DeciCelsius = DeciCelsius::new(-400);
+/// Temperature difference between columns
+pub const TEMPERATURE_STEP: DeciCelsius = DeciCelsius::new(100);
+/// Number of columns, up to +80 °C
+pub const TEMPERATURE_POINTS: usize = 13;
+
+/// Battery voltage of the first table row
+pub const VOLTAGE_MIN_MV: u16 = 1800;
+/// Battery voltage difference between rows
+pub const VOLTAGE_STEP_MV: u16 = 300;
+/// Number of rows, up to 3.3 V
+pub const VOLTAGE_POINTS: usize = 6;
+
+/// Resolution of the stored frequencies, allowing up to 262 kHz to be stored in
+/// 16 bits
+const FREQUENCY_UNIT_HZ: u32 = 4;
+
+/// Identifies a stored table, and must be changed if the layout changes
+const MARKER: u8 = 0xc7;
+const CRC_POLY: u8 = 0x07;
+
+/// Conditions under which the watchdog frequency was measured
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Conditions {
+    pub temperature: DeciCelsius,
+    pub battery_mv: u16,
+}
+
+/// Index of the closest grid point, clamped to the table
+const fn grid_index(value: i32, min: i32, step: i32, points: usize) -> usize {
+    let index = (value - min + step / 2).div_euclid(step);
+    if index < 0 {
+        0
+    } else if index >= points as i32 {
+        points - 1
+    } else {
+        index as usize
+    }
+}
+
+/// Size of a serialized table: marker, cells and CRC
+pub const SIZE: usize = 1 + 2 * TEMPERATURE_POINTS * VOLTAGE_POINTS + 1;
+
+/// Bytes holding a serialized table, such as EEPROM. Cells are read from it as
+/// they are needed, so the table doesn't have to fit in RAM.
+pub trait Storage {
+    fn read(&self, index: usize) -> u8;
+    fn write(&mut self, index: usize, value: u8);
+}
+
+impl Storage for [u8; SIZE] {
+    fn read(&self, index: usize) -> u8 {
+        self[index]
+    }
+
+    fn write(&mut self, index: usize, value: u8) {
+        self[index] = value;
+    }
+}
+
+/// Watchdog frequencies measured at a grid of temperatures and voltages. Each
+/// cell holds a frequency in units of 4 Hz, or zero if not measured.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Table<S = [u8; SIZE]> {
+    storage: S,
+    /// Whether the storage holds a table with a valid marker and CRC. If not,
+    /// the table is empty and the storage is overwritten by the first
+    /// measurement.
+    valid: bool,
+}
+
+impl Default for Table {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Table {
+    /// Create a table with no measurements, held in RAM.
+    pub const fn new() -> Self {
+        Self {
+            storage: [0; SIZE],
+            valid: false,
+        }
+    }
+}
+
+impl<S: Storage> Table<S> {
+    /// Use the table held in `storage`, which is empty unless a valid table is
+    /// stored.
+    pub fn open(storage: S) -> Self {
+        let valid = storage.read(0) == MARKER && Self::crc(&storage) == storage.read(SIZE - 1);
+        Self { storage, valid }
+    }
+
+    /// Use the table held in `storage`, when it is already known from
+    /// [`Self::is_valid`] whether a valid table is stored. This avoids reading
+    /// the whole table to check the CRC.
+    pub const fn open_known(storage: S, valid: bool) -> Self {
+        Self { storage, valid }
+    }
+
+    /// Whether the storage holds a valid table. It becomes valid once the first
+    /// measurement is recorded.
+    pub const fn is_valid(&self) -> bool {
+        self.valid
+    }
+
+    pub fn into_storage(self) -> S {
+        self.storage
+    }
+
+    fn crc(storage: &S) -> u8 {
+        (0..SIZE - 1).fold(0, |crc, i| crc8(&[storage.read(i)], CRC_POLY, crc))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        (0..VOLTAGE_POINTS)
+            .all(|row| (0..TEMPERATURE_POINTS).all(|column| self.cell(row, column) == 0))
+    }
+
+    const fn column(temperature: DeciCelsius) -> usize {
+        grid_index(
+            temperature.tenths() as i32,
+            TEMPERATURE_MIN.tenths() as i32,
+            TEMPERATURE_STEP.tenths() as i32,
+            TEMPERATURE_POINTS,
+        )
+    }
+
+    const fn row(battery_mv: u16) -> usize {
+        grid_index(
+            battery_mv as i32,
+            VOLTAGE_MIN_MV as i32,
+            VOLTAGE_STEP_MV as i32,
+            VOLTAGE_POINTS,
+        )
+    }
+
+    /// Index of the first byte of a cell in the storage
+    const fn cell_index(row: usize, column: usize) -> usize {
+        1 + 2 * (row * TEMPERATURE_POINTS + column)
+    }
+
+    fn cell(&self, row: usize, column: usize) -> u16 {
+        if !self.valid {
+            return 0;
+        }
+        let index = Self::cell_index(row, column);
+        u16::from_le_bytes([self.storage.read(index), self.storage.read(index + 1)])
+    }
+
+    /// Write a cell and update the CRC, first clearing the storage if it
+    /// didn't hold a valid table
+    fn set_cell(&mut self, row: usize, column: usize, value: u16) {
+        if !self.valid {
+            self.storage.write(0, MARKER);
+            for i in 1..SIZE - 1 {
+                self.storage.write(i, 0);
+            }
+            self.valid = true;
+        }
+        let index = Self::cell_index(row, column);
+        let [low, high] = value.to_le_bytes();
+        self.storage.write(index, low);
+        self.storage.write(index + 1, high);
+        let crc = Self::crc(&self.storage);
+        self.storage.write(SIZE - 1, crc);
+    }
+
+    /// Record a frequency measured under `conditions` in the closest cell,
+    /// averaging it with any previous measurement. Returns whether the table
+    /// changed, and only writes to the storage if it did.
+    pub fn record(&mut self, conditions: Conditions, frequency_hz: u32) -> bool {
+        let measured = ((frequency_hz + FREQUENCY_UNIT_HZ / 2) / FREQUENCY_UNIT_HZ)
+            .clamp(1, u16::MAX as u32) as u16;
+        let (row, column) = (
+            Self::row(conditions.battery_mv),
+            Self::column(conditions.temperature),
+        );
+        let cell = self.cell(row, column);
+        let value = if cell == 0 {
+            measured
+        } else {
+            // Round half up
+            ((cell as u32 + measured as u32 + 1) >> 1) as u16
+        };
+        if cell == value {
+            return false;
+        }
+        self.set_cell(row, column, value);
+        true
+    }
+
+    /// Frequency predicted under `conditions`, interpolated between the closest
+    /// measured temperatures in the closest row that has any measurements.
+    pub fn frequency_hz(&self, conditions: Conditions) -> Option<u32> {
+        let nearest = Self::row(conditions.battery_mv);
+        let row = (0..VOLTAGE_POINTS)
+            .flat_map(|d| [nearest.checked_sub(d), Some(nearest + d)])
+            .flatten()
+            .filter(|r| *r < VOLTAGE_POINTS)
+            .find(|r| (0..TEMPERATURE_POINTS).any(|c| self.cell(*r, c) != 0))?;
+        let cell = |column: usize| self.cell(row, column);
+
+        let temperature = conditions.temperature.tenths() as i32;
+        let point = |i: usize| {
+            TEMPERATURE_MIN.tenths() as i32 + i as i32 * TEMPERATURE_STEP.tenths() as i32
+        };
+        let below = (0..TEMPERATURE_POINTS)
+            .rev()
+            .find(|i| cell(*i) != 0 && point(*i) <= temperature);
+        let above = (0..TEMPERATURE_POINTS).find(|i| cell(*i) != 0 && point(*i) >= temperature);
+
+        let value = match (below, above) {
+            (Some(b), Some(a)) if a != b => {
+                let (fb, fa) = (cell(b) as i32, cell(a) as i32);
+                fb + (fa - fb) * (temperature - point(b)) / (point(a) - point(b))
+            }
+            (Some(i), _) | (_, Some(i)) => cell(i) as i32,
+            (None, None) => unreachable!(),
+        };
+        Some(value as u32 * FREQUENCY_UNIT_HZ)
+    }
+
+    /// Correct `frequency_hz`, measured under `measured`, for the current
+    /// `conditions`. The frequency is unchanged if there are no measurements.
+    pub fn correct(&self, frequency_hz: u32, measured: Conditions, conditions: Conditions) -> u32 {
+        match (self.frequency_hz(measured), self.frequency_hz(conditions)) {
+            (Some(from), Some(to)) => (frequency_hz as u64 * to as u64 / from as u64) as u32,
+            _ => frequency_hz,
+        }
+    }
+}
@@ -0,0 +1,132 @@
+//! Device configuration stored in EEPROM
+//!
+//! The configuration is stored as a marker, a layout version, the fields of
+//! that version and a CRC. Fields are only ever appended to the layout, so a
+//! configuration stored by older firmware is migrated by reading the fields it
+//! has and using the defaults for the rest.
+
+use core::ops::RangeInclusive;
+
+use acurite_protocol::{crc8, DeciCelsius};
+
+use crate::{sleep, CycleConfig};
+
+/// Identifies a stored configuration
+const MARKER: u8 = 0xa5;
+const CRC_POLY: u8 = 0x07;
+
+/// Current layout version
+pub const VERSION: u8 = 2;
+
+/// Size of the fields of each layout version, starting with version 1
+const PAYLOAD_SIZES: [usize; VERSION as usize] = [10, 12];
+
+/// Supported transmit periods. Longer periods don't fit in a watchdog sleep
+/// schedule.
+pub const PERIOD_RANGE_MS: RangeInclusive<u32> = 1_000..=sleep::PERIOD_MS;
+
+/// Supported temperature calibration offsets, in tenths of a degree
+pub const OFFSET_RANGE: RangeInclusive<i16> = -100..=100;
+
+/// TMP102 configuration register: one-shot, shutdown and extended mode
+pub const DEFAULT_SENSOR_CONFIG: u16 = 0x8110;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Config {
+    /// Transmitter ID included in each message
+    pub id: u8,
+    /// The battery is reported as low below this voltage
+    pub battery_low_mv: u16,
+    /// Number of times each message is sent, or `None` to use the protocol
+    /// default
+    pub repeats: Option<u8>,
+    /// Time between transmissions
+    pub period_ms: u32,
+    /// Value written to the TMP102 configuration register
+    pub sensor_config: u16,
+    /// Added to each temperature measurement
+    pub temperature_offset: DeciCelsius,
+}
+
+impl Config {
+    /// Size of the header and CRC
+    const OVERHEAD: usize = 3;
+    /// Size of the current layout
+    pub const SIZE: usize = Self::OVERHEAD + PAYLOAD_SIZES[VERSION as usize - 1];
+
+    pub const DEFAULT: Self = Self {
+        id: 0,
+        battery_low_mv: 2000,
+        repeats: None,
+        period_ms: sleep::PERIOD_MS,
+        sensor_config: DEFAULT_SENSOR_CONFIG,
+        temperature_offset: DeciCelsius::new(0),
+    };
+
+    /// Parameters of each cycle
+    pub const fn cycle(&self) -> CycleConfig {
+        CycleConfig {
+            battery_low_mv: self.battery_low_mv,
+            repeats: self.repeats,
+        }
+    }
+
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        let mut bytes = [0; Self::SIZE];
+        bytes[0] = MARKER;
+        bytes[1] = VERSION;
+        bytes[2] = self.id;
+        bytes[3..5].copy_from_slice(&self.battery_low_mv.to_le_bytes());
+        bytes[5] = self.repeats.unwrap_or(0);
+        bytes[6..10].copy_from_slice(&self.period_ms.to_le_bytes());
+        bytes[10..12].copy_from_slice(&self.sensor_config.to_le_bytes());
+        bytes[12..14].copy_from_slice(&self.temperature_offset.tenths().to_le_bytes());
+        bytes[Self::SIZE - 1] = crc8(&bytes[..Self::SIZE - 1], CRC_POLY, 0);
+        bytes
+    }
+
+    /// Deserialize a stored configuration of any layout version up to the
+    /// current one, returning `None` if no valid configuration is stored.
+    /// `bytes` may be longer than the stored configuration. Fields outside of
+    /// their supported range are clamped to it, rather than discarding the
+    /// rest of the configuration and with it the paired ID.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let (&marker, rest) = bytes.split_first()?;
+        let &version = rest.first()?;
+        if marker != MARKER || version == 0 || version > VERSION {
+            return None;
+        }
+        let size = Self::OVERHEAD + PAYLOAD_SIZES[version as usize - 1];
+        let bytes = bytes.get(..size)?;
+        if crc8(&bytes[..size - 1], CRC_POLY, 0) != bytes[size - 1] {
+            return None;
+        }
+
+        Some(Self {
+            id: bytes[2],
+            battery_low_mv: u16::from_le_bytes([bytes[3], bytes[4]]),
+            repeats: match bytes[5] {
+                0 => None,
+                repeats => Some(repeats),
+            },
+            period_ms: u32::from_le_bytes([bytes[6], bytes[7], bytes[8], bytes[9]])
+                .clamp(*PERIOD_RANGE_MS.start(), *PERIOD_RANGE_MS.end()),
+            sensor_config: u16::from_le_bytes([bytes[10], bytes[11]]),
+            // Added in version 2
+            temperature_offset: if version >= 2 {
+                DeciCelsius::new(
+                    i16::from_le_bytes([bytes[12], bytes[13]])
+                        .clamp(*OFFSET_RANGE.start(), *OFFSET_RANGE.end()),
+                )
+            } else {
+                Self::DEFAULT.temperature_offset
+            },
+        })
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
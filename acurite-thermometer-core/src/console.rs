@@ -0,0 +1,248 @@
+//! Line-based configuration and diagnostics console
+//!
+//! Commands:
+//! - `get [setting]`: print one or all settings
+//! - `set <setting> <value>`: change and save a setting
+//! - `measure`: take a measurement immediately
+//! - `stats`: print cycle statistics
+//! - `defaults`: reset all settings except the ID to their defaults
+//! - `help`: list the commands
+
+use core::ops::RangeInclusive;
+
+use acurite_protocol::DeciCelsius;
+
+use crate::config::{Config, OFFSET_RANGE};
+
+/// Maximum number of times each message can be sent
+pub const MAX_REPEATS: u8 = 20;
+
+pub const HELP: &str =
+    "commands: get [setting], set <setting> <value>, measure, stats, defaults, help
+settings: id, battery (mV), offset (C), repeats (0 = protocol default)";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    LineTooLong,
+    UnknownCommand,
+    UnknownSetting,
+    MissingArgument,
+    UnexpectedArgument,
+    InvalidValue,
+    OutOfRange,
+}
+
+impl Error {
+    pub const fn message(self) -> &'static str {
+        match self {
+            Self::LineTooLong => "line too long",
+            Self::UnknownCommand => "unknown command",
+            Self::UnknownSetting => "unknown setting",
+            Self::MissingArgument => "missing argument",
+            Self::UnexpectedArgument => "unexpected argument",
+            Self::InvalidValue => "invalid value",
+            Self::OutOfRange => "value out of range",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Setting {
+    /// Transmitter ID
+    Id,
+    /// Low battery threshold in mV
+    Battery,
+    /// Temperature calibration offset in tenths of a degree
+    Offset,
+    /// Number of times each message is sent, or 0 for the protocol default
+    Repeats,
+}
+
+impl Setting {
+    pub const ALL: [Self; 4] = [Self::Id, Self::Battery, Self::Offset, Self::Repeats];
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Id => "id",
+            Self::Battery => "battery",
+            Self::Offset => "offset",
+            Self::Repeats => "repeats",
+        }
+    }
+
+    fn from_name(name: &str) -> Result<Self, Error> {
+        Self::ALL
+            .into_iter()
+            .find(|s| s.name() == name)
+            .ok_or(Error::UnknownSetting)
+    }
+
+    const fn range(self) -> RangeInclusive<i32> {
+        match self {
+            Self::Id => 0..=u8::MAX as i32,
+            Self::Battery => 0..=u16::MAX as i32,
+            Self::Offset => *OFFSET_RANGE.start() as i32..=*OFFSET_RANGE.end() as i32,
+            Self::Repeats => 0..=MAX_REPEATS as i32,
+        }
+    }
+
+    /// Parse a value, which is in degrees with up to one decimal place for the
+    /// offset and an integer otherwise
+    fn parse_value(self, value: &str) -> Result<i32, Error> {
+        let value = match self {
+            Self::Offset => parse_tenths(value),
+            _ => value.parse().ok(),
+        }
+        .ok_or(Error::InvalidValue)?;
+        if !self.range().contains(&value) {
+            return Err(Error::OutOfRange);
+        }
+        Ok(value)
+    }
+
+    pub fn get(self, config: &Config) -> i32 {
+        match self {
+            Self::Id => config.id.into(),
+            Self::Battery => config.battery_low_mv.into(),
+            Self::Offset => config.temperature_offset.tenths().into(),
+            Self::Repeats => config.repeats.unwrap_or(0).into(),
+        }
+    }
+
+    pub fn set(self, config: &mut Config, value: i32) -> Result<(), Error> {
+        if !self.range().contains(&value) {
+            return Err(Error::OutOfRange);
+        }
+        match self {
+            Self::Id => config.id = value as u8,
+            Self::Battery => config.battery_low_mv = value as u16,
+            Self::Offset => config.temperature_offset = DeciCelsius::new(value as i16),
+            Self::Repeats => {
+                config.repeats = match value {
+                    0 => None,
+                    repeats => Some(repeats as u8),
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parse a decimal number with up to one decimal place into tenths
+fn parse_tenths(value: &str) -> Option<i32> {
+    let (negative, value) = match value.strip_prefix('-') {
+        Some(value) => (true, value),
+        None => (false, value),
+    };
+    let (whole, fraction) = value.split_once('.').unwrap_or((value, "0"));
+    if whole.is_empty()
+        || fraction.len() != 1
+        || !whole
+            .bytes()
+            .chain(fraction.bytes())
+            .all(|b| b.is_ascii_digit())
+    {
+        return None;
+    }
+    let tenths = whole.parse::<i32>().ok()?.checked_mul(10)? + fraction.parse::<i32>().ok()?;
+    Some(if negative { -tenths } else { tenths })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    /// Print a setting, or all of them
+    Get(Option<Setting>),
+    Set(Setting, i32),
+    Measure,
+    Stats,
+    Defaults,
+    Help,
+}
+
+impl Command {
+    pub fn parse(line: &str) -> Result<Self, Error> {
+        let mut words = line.split_ascii_whitespace();
+        let command = match words.next().ok_or(Error::UnknownCommand)? {
+            "get" => Self::Get(words.next().map(Setting::from_name).transpose()?),
+            "set" => {
+                let setting = Setting::from_name(words.next().ok_or(Error::MissingArgument)?)?;
+                let value = words.next().ok_or(Error::MissingArgument)?;
+                Self::Set(setting, setting.parse_value(value)?)
+            }
+            "measure" => Self::Measure,
+            "stats" => Self::Stats,
+            "defaults" => Self::Defaults,
+            "help" => Self::Help,
+            _ => return Err(Error::UnknownCommand),
+        };
+        match words.next() {
+            Some(_) => Err(Error::UnexpectedArgument),
+            None => Ok(command),
+        }
+    }
+}
+
+/// Assembles received bytes into lines
+#[derive(Debug, Clone)]
+pub struct LineBuffer<const N: usize> {
+    bytes: [u8; N],
+    len: usize,
+    overflowed: bool,
+    /// The last line was returned, so the next byte starts a new one
+    complete: bool,
+}
+
+impl<const N: usize> Default for LineBuffer<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> LineBuffer<N> {
+    pub const fn new() -> Self {
+        Self {
+            bytes: [0; N],
+            len: 0,
+            overflowed: false,
+            complete: false,
+        }
+    }
+
+    /// Add a received byte, returning the line once it is terminated by CR or
+    /// LF. Empty lines are ignored and backspace removes the last character.
+    pub fn push(&mut self, byte: u8) -> Option<Result<&str, Error>> {
+        if self.complete {
+            self.len = 0;
+            self.overflowed = false;
+            self.complete = false;
+        }
+        match byte {
+            b'\r' | b'\n' => {
+                if self.len == 0 && !self.overflowed {
+                    return None;
+                }
+                self.complete = true;
+                if self.overflowed {
+                    return Some(Err(Error::LineTooLong));
+                }
+                Some(
+                    core::str::from_utf8(&self.bytes[..self.len])
+                        .map_err(|_| Error::UnknownCommand),
+                )
+            }
+            0x08 | 0x7f => {
+                self.len = self.len.saturating_sub(1);
+                None
+            }
+            _ => {
+                if self.len < N {
+                    self.bytes[self.len] = byte;
+                    self.len += 1;
+                } else {
+                    self.overflowed = true;
+                }
+                None
+            }
+        }
+    }
+}
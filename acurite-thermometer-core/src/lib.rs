@@ -0,0 +1,163 @@
+//! Hardware independent parts of the thermometer firmware, which can be tested
+//! on the host.
+#![no_std]
+
+use acurite_protocol::{pulse::Pulse, DeciCelsius};
+
+pub mod compensation;
+pub mod config;
+pub mod console;
+pub mod poll;
+pub mod protocol;
+pub mod sleep;
+#[cfg(test)]
+mod test;
+pub mod tmp102;
+pub mod tmp117;
+
+pub use protocol::Protocol;
+
+/// Hardware used by the firmware.
+pub trait Board {
+    type SensorError;
+
+    /// Take a single temperature measurement.
+    fn read_temperature(&mut self) -> Result<DeciCelsius, Self::SensorError>;
+
+    fn read_battery_mv(&mut self) -> u16;
+
+    /// Send a sequence of pulses over the radio.
+    fn transmit(&mut self, pulses: impl Iterator<Item = Pulse>);
+
+    fn set_led(&mut self, on: bool);
+
+    fn delay_ms(&mut self, ms: u16);
+
+    /// Sleep until it is time for the next transmission.
+    fn sleep(&mut self);
+
+    /// Called with the result of each cycle before going to sleep, for example
+    /// to log it.
+    fn report<P: Protocol>(
+        &mut self,
+        _result: &Result<Report, CycleError<Self::SensorError, P::Error>>,
+    ) {
+    }
+
+    /// Number of times the LED blinks if a message could not be sent because
+    /// of `error`. Each kind of error should blink a different number of
+    /// times, so they can be told apart.
+    fn error_blinks<P: Protocol>(&self, _error: &CycleError<Self::SensorError, P::Error>) -> u8 {
+        ERROR_BLINKS
+    }
+}
+
+/// Measurements taken during a successful cycle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Report {
+    pub temperature: DeciCelsius,
+    pub battery_mv: u16,
+}
+
+/// Counts of cycle results since boot
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Stats {
+    pub cycles: u32,
+    pub sensor_errors: u32,
+    pub encode_errors: u32,
+    /// Measurements of the last successful cycle
+    pub last: Option<Report>,
+}
+
+impl Stats {
+    pub fn record<S, P>(&mut self, result: &Result<Report, CycleError<S, P>>) {
+        self.cycles += 1;
+        match result {
+            Ok(report) => self.last = Some(*report),
+            Err(CycleError::Sensor(_)) => self.sensor_errors += 1,
+            Err(CycleError::Encode(_)) => self.encode_errors += 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CycleError<S, P> {
+    /// The temperature sensor could not be read.
+    Sensor(S),
+    /// The measurements could not be encoded into a message, usually because
+    /// they are out of range.
+    Encode(P),
+}
+
+/// Parameters of each cycle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CycleConfig {
+    /// The battery is reported as low below this voltage
+    pub battery_low_mv: u16,
+    /// Number of times each message is sent, or `None` to use the protocol
+    /// default
+    pub repeats: Option<u8>,
+}
+
+/// Number of times the LED blinks if a message could not be sent, unless the
+/// board blinks differently for the error
+pub const ERROR_BLINKS: u8 = 4;
+
+fn measure_and_transmit<B: Board, P: Protocol>(
+    board: &mut B,
+    protocol: &mut P,
+    config: &CycleConfig,
+) -> Result<Report, CycleError<B::SensorError, P::Error>> {
+    board.set_led(true);
+    let temperature = board.read_temperature();
+    board.set_led(false);
+    let temperature = temperature.map_err(CycleError::Sensor)?;
+
+    let battery_mv = board.read_battery_mv();
+    let pulses = protocol
+        .encode(temperature, battery_mv > config.battery_low_mv)
+        .map_err(CycleError::Encode)?;
+
+    for _ in 0..config.repeats.map_or(P::REPEATS, usize::from) {
+        board.transmit(pulses.clone());
+    }
+
+    Ok(Report {
+        temperature,
+        battery_mv,
+    })
+}
+
+/// Measure, encode and transmit a message, then report the result. If a
+/// message could not be sent, the LED blinks instead. This can be used to
+/// transmit for several sensors before sleeping.
+pub fn run_sensor<B: Board, P: Protocol>(
+    board: &mut B,
+    protocol: &mut P,
+    config: &CycleConfig,
+) -> Result<Report, CycleError<B::SensorError, P::Error>> {
+    let result = measure_and_transmit(board, protocol, config);
+    board.report::<P>(&result);
+
+    if let Err(error) = &result {
+        for _ in 0..board.error_blinks::<P>(error) {
+            board.delay_ms(100);
+            board.set_led(true);
+            board.delay_ms(100);
+            board.set_led(false);
+        }
+    }
+    result
+}
+
+/// Run a single measure, encode, transmit and sleep cycle. If a message could
+/// not be sent, the LED blinks instead.
+pub fn run_cycle<B: Board, P: Protocol>(
+    board: &mut B,
+    protocol: &mut P,
+    config: &CycleConfig,
+) -> Result<Report, CycleError<B::SensorError, P::Error>> {
+    let result = run_sensor(board, protocol, config);
+    board.sleep();
+    result
+}
@@ -0,0 +1,30 @@
+//! Polling a sensor until it finishes an operation
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PollError<E> {
+    /// Checking whether the operation finished failed
+    Check(E),
+    /// The operation didn't finish after the maximum number of polls
+    Timeout,
+}
+
+/// Call `done` until it returns true, calling `wait` between calls. Gives up
+/// after `done` has returned false `max_polls` times, but always checks at
+/// least once. `context` is passed to both functions, so that they can share
+/// mutable access to a driver.
+pub fn poll<C, E>(
+    context: &mut C,
+    max_polls: u8,
+    mut done: impl FnMut(&mut C) -> Result<bool, E>,
+    mut wait: impl FnMut(&mut C),
+) -> Result<(), PollError<E>> {
+    let mut polls = 0;
+    while !done(context).map_err(PollError::Check)? {
+        polls += 1;
+        if polls >= max_polls {
+            return Err(PollError::Timeout);
+        }
+        wait(context);
+    }
+    Ok(())
+}
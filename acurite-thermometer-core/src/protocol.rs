@@ -0,0 +1,126 @@
+//! Adapters that turn a temperature reading into a message for each supported
+//! sensor protocol.
+
+use acurite_protocol::{pulse::Pulse, tx00606, tx592, tx609, tx986, DeciCelsius};
+
+pub trait Protocol {
+    type Pulses: Iterator<Item = Pulse> + Clone;
+    type Error;
+
+    /// Number of times each message is sent
+    const REPEATS: usize;
+
+    /// Encode a message containing the current measurements.
+    fn encode(
+        &mut self,
+        temperature: DeciCelsius,
+        battery_ok: bool,
+    ) -> Result<Self::Pulses, Self::Error>;
+}
+
+/// Acurite 00606TX temperature sensor
+pub struct Tx00606 {
+    pub id: u8,
+}
+
+impl Protocol for Tx00606 {
+    type Pulses = acurite_protocol::pulse::Pulses<4>;
+    type Error = tx00606::Error;
+
+    const REPEATS: usize = tx00606::REPEATS;
+
+    fn encode(
+        &mut self,
+        temperature: DeciCelsius,
+        battery_ok: bool,
+    ) -> Result<Self::Pulses, Self::Error> {
+        Ok(tx00606::Message::new(self.id, battery_ok, temperature)?.pulses())
+    }
+}
+
+/// Acurite 592TXR temperature and humidity sensor
+pub struct Tx592 {
+    pub channel: tx592::Channel,
+    pub id: u16,
+    /// There is no humidity sensor, so a fixed value is reported
+    pub humidity: u8,
+    sequence: u8,
+}
+
+impl Tx592 {
+    pub const fn new(channel: tx592::Channel, id: u16, humidity: u8) -> Self {
+        Self {
+            channel,
+            id,
+            humidity,
+            sequence: 0,
+        }
+    }
+}
+
+impl Protocol for Tx592 {
+    type Pulses = acurite_protocol::pulse::Pulses<7>;
+    type Error = tx592::Error;
+
+    const REPEATS: usize = tx592::REPEATS;
+
+    fn encode(
+        &mut self,
+        temperature: DeciCelsius,
+        battery_ok: bool,
+    ) -> Result<Self::Pulses, Self::Error> {
+        let message = tx592::Message::new(
+            self.channel,
+            self.sequence,
+            self.id,
+            battery_ok,
+            self.humidity,
+            temperature,
+        )?;
+        self.sequence = (self.sequence + 1) & tx592::SEQUENCE_MAX;
+        Ok(message.pulses())
+    }
+}
+
+/// Acurite 609TXC temperature and humidity sensor
+pub struct Tx609 {
+    pub id: u8,
+    /// There is no humidity sensor, so a fixed value is reported
+    pub humidity: u8,
+}
+
+impl Protocol for Tx609 {
+    type Pulses = acurite_protocol::pulse::Pulses<5>;
+    type Error = tx609::Error;
+
+    const REPEATS: usize = tx609::REPEATS;
+
+    fn encode(
+        &mut self,
+        temperature: DeciCelsius,
+        battery_ok: bool,
+    ) -> Result<Self::Pulses, Self::Error> {
+        Ok(tx609::Message::new(self.id, battery_ok, self.humidity, temperature)?.pulses())
+    }
+}
+
+/// Acurite 986 refrigerator/freezer sensor
+pub struct Tx986 {
+    pub sensor: tx986::Sensor,
+    pub id: u16,
+}
+
+impl Protocol for Tx986 {
+    type Pulses = acurite_protocol::pulse::Pulses<5>;
+    type Error = tx986::Error;
+
+    const REPEATS: usize = tx986::REPEATS;
+
+    fn encode(
+        &mut self,
+        temperature: DeciCelsius,
+        battery_ok: bool,
+    ) -> Result<Self::Pulses, Self::Error> {
+        Ok(tx986::Message::from_celsius(self.sensor, self.id, battery_ok, temperature)?.pulses())
+    }
+}
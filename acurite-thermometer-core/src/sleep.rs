@@ -0,0 +1,294 @@
+//! Watchdog timed sleep between transmissions
+
+use crate::compensation::{Conditions, Storage, Table};
+
+/// Nominal frequency of the watchdog oscillator
+pub const WDT_FREQUENCY_HZ: u32 = 128_000;
+
+/// Interval between transmissions expected by the display
+pub const PERIOD_MS: u32 = 31_000;
+const PERIOD_US: u32 = PERIOD_MS * 1000;
+
+/// Watchdog timeout. Each timeout is a fixed number of watchdog oscillator
+/// cycles, so the actual duration depends on the oscillator frequency.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(u8)]
+pub enum Timeout {
+    Ms16,
+    Ms32,
+    Ms64,
+    Ms125,
+    Ms250,
+    Ms500,
+    Ms1000,
+    Ms2000,
+    Ms4000,
+    Ms8000,
+}
+
+impl Timeout {
+    /// Value of the WDP prescaler bits
+    pub const fn prescaler(self) -> u8 {
+        self as u8
+    }
+
+    const fn from_prescaler(prescaler: u8) -> Self {
+        match prescaler {
+            0 => Self::Ms16,
+            1 => Self::Ms32,
+            2 => Self::Ms64,
+            3 => Self::Ms125,
+            4 => Self::Ms250,
+            5 => Self::Ms500,
+            6 => Self::Ms1000,
+            7 => Self::Ms2000,
+            8 => Self::Ms4000,
+            _ => Self::Ms8000,
+        }
+    }
+
+    /// Number of watchdog oscillator cycles before the timeout expires
+    pub const fn cycles(self) -> u32 {
+        2048 << self as u32
+    }
+}
+
+/// Maximum number of timeouts in a schedule, enough for a watchdog running at
+/// twice its nominal frequency
+pub const MAX_SCHEDULE_LEN: usize = 16;
+
+/// Sequence of watchdog timeouts that make up a sleep period
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Schedule {
+    timeouts: [Timeout; MAX_SCHEDULE_LEN],
+    len: usize,
+}
+
+impl Schedule {
+    /// Plan the shortest schedule that lasts as close as possible to `cycles`
+    /// watchdog oscillator cycles. Schedules longer than [`MAX_SCHEDULE_LEN`]
+    /// are truncated, and the schedule is never empty, since the watchdog has
+    /// to be started even if there is no time left to sleep.
+    pub const fn new(cycles: u32) -> Self {
+        // Every timeout is a multiple of the shortest one, so first round to
+        // the nearest multiple of it
+        let unit = Timeout::Ms16.cycles();
+        let below = cycles / unit;
+        let remainder = cycles % unit;
+        let units = if remainder < unit / 2 {
+            below
+        } else if remainder > unit / 2 {
+            below + 1
+        } else if Self::len_for(below) < Self::len_for(below + 1) {
+            // Exactly half way, so pick the shorter schedule
+            below
+        } else {
+            below + 1
+        };
+        Self::from_units(if units == 0 { 1 } else { units })
+    }
+
+    /// Length of the shortest schedule lasting `units` of the shortest timeout
+    const fn len_for(units: u32) -> u32 {
+        let max_units = Timeout::Ms8000.cycles() / Timeout::Ms16.cycles();
+        units / max_units + (units % max_units).count_ones()
+    }
+
+    /// Since each timeout is twice as long as the previous one, the shortest
+    /// schedule is as many of the longest timeout as possible, followed by one
+    /// of each timeout corresponding to a set bit of the remainder.
+    const fn from_units(units: u32) -> Self {
+        let mut schedule = Self {
+            timeouts: [Timeout::Ms16; MAX_SCHEDULE_LEN],
+            len: 0,
+        };
+        let max_prescaler = Timeout::Ms8000.prescaler();
+        let mut longest = units >> max_prescaler;
+        while longest > 0 && schedule.len < MAX_SCHEDULE_LEN {
+            schedule.timeouts[schedule.len] = Timeout::Ms8000;
+            schedule.len += 1;
+            longest -= 1;
+        }
+        let mut prescaler = max_prescaler;
+        while prescaler > 0 && schedule.len < MAX_SCHEDULE_LEN {
+            prescaler -= 1;
+            if units & (1 << prescaler) != 0 {
+                schedule.timeouts[schedule.len] = Timeout::from_prescaler(prescaler);
+                schedule.len += 1;
+            }
+        }
+        schedule
+    }
+
+    pub fn timeouts(&self) -> &[Timeout] {
+        &self.timeouts[..self.len]
+    }
+
+    /// Total number of watchdog oscillator cycles in the schedule
+    pub fn cycles(&self) -> u32 {
+        sequence_cycles(self.timeouts())
+    }
+}
+
+/// Total number of watchdog oscillator cycles in a sleep sequence
+pub const fn sequence_cycles(sequence: &[Timeout]) -> u32 {
+    let mut cycles = 0;
+    let mut i = 0;
+    while i < sequence.len() {
+        cycles += sequence[i].cycles();
+        i += 1;
+    }
+    cycles
+}
+
+/// Plan the shortest schedule that makes a period last as close as possible to
+/// `target_us`, for a watchdog running at `frequency_hz`. `awake_us` is the part
+/// of the period that is not covered by the schedule.
+pub const fn plan(target_us: u32, frequency_hz: u32, awake_us: u32) -> Schedule {
+    let sleep_us = target_us.saturating_sub(awake_us) as u64;
+    Schedule::new(((sleep_us * frequency_hz as u64 + 500_000) / 1_000_000) as u32)
+}
+
+/// Watchdog timeout that is timed against the CPU clock during calibration.
+/// Calibration waits for one timeout to synchronize with the watchdog before
+/// measuring the next one.
+pub const CALIBRATION_TIMEOUT: Timeout = Timeout::Ms64;
+
+/// Number of sleep periods between calibrations
+pub const CALIBRATION_INTERVAL: u16 = 20;
+
+/// Lowest and highest watchdog frequencies accepted from a calibration.
+/// Anything outside this range is assumed to be a measurement error.
+pub const CALIBRATION_RANGE_HZ: (u32, u32) = (WDT_FREQUENCY_HZ / 2, WDT_FREQUENCY_HZ * 2);
+
+/// Time that calibration takes for a watchdog running at `frequency_hz`
+pub const fn calibration_us(frequency_hz: u32) -> u32 {
+    (2 * CALIBRATION_TIMEOUT.cycles() as u64 * 1_000_000 / frequency_hz as u64) as u32
+}
+
+/// Watchdog frequency measured by counting `cpu_cycles` during
+/// [`CALIBRATION_TIMEOUT`], or `None` if the measurement is implausible.
+pub const fn calibrated_frequency_hz(cpu_cycles: u32, cpu_hz: u32) -> Option<u32> {
+    if cpu_cycles == 0 {
+        return None;
+    }
+    let frequency_hz =
+        (CALIBRATION_TIMEOUT.cycles() as u64 * cpu_hz as u64 / cpu_cycles as u64) as u32;
+    if frequency_hz < CALIBRATION_RANGE_HZ.0 || frequency_hz > CALIBRATION_RANGE_HZ.1 {
+        None
+    } else {
+        Some(frequency_hz)
+    }
+}
+
+/// Keeps the sleep schedule matched to the measured watchdog frequency. The
+/// compensation table is passed in when needed rather than kept here, since
+/// the firmware reads it from EEPROM.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Calibration {
+    frequency_hz: u32,
+    /// Conditions during the last calibration, if known
+    conditions: Option<Conditions>,
+    period_us: u32,
+    interval: u16,
+    /// Sleep periods until the next calibration
+    remaining: u16,
+}
+
+impl Calibration {
+    /// Calibrate every `interval` sleep periods, starting immediately.
+    pub const fn new(interval: u16) -> Self {
+        Self {
+            frequency_hz: WDT_FREQUENCY_HZ,
+            conditions: None,
+            period_us: PERIOD_US,
+            interval,
+            remaining: 0,
+        }
+    }
+
+    /// Sleep for `period_ms` instead of [`PERIOD_MS`]
+    pub const fn period_ms(mut self, period_ms: u32) -> Self {
+        self.period_us = period_ms * 1000;
+        self
+    }
+
+    /// Frequency measured during the last calibration
+    pub const fn frequency_hz(&self) -> u32 {
+        self.frequency_hz
+    }
+
+    /// Whether the watchdog should be calibrated before starting the next
+    /// sleep period
+    pub const fn due(&self) -> bool {
+        self.remaining == 0
+    }
+
+    /// Update the frequency using the number of CPU cycles counted during
+    /// [`CALIBRATION_TIMEOUT`] under `conditions`. Implausible measurements are
+    /// ignored, and the return value indicates whether the measurement was
+    /// used.
+    pub fn update(&mut self, cpu_cycles: u32, cpu_hz: u32, conditions: Option<Conditions>) -> bool {
+        self.remaining = self.interval;
+        let Some(frequency_hz) = calibrated_frequency_hz(cpu_cycles, cpu_hz) else {
+            return false;
+        };
+        self.frequency_hz = frequency_hz;
+        self.conditions = conditions;
+        true
+    }
+
+    /// Record the last calibration in the compensation table, if the
+    /// conditions were known. Returns whether the table changed.
+    pub fn record<S: Storage>(&self, table: &mut Table<S>) -> bool {
+        match self.conditions {
+            Some(conditions) => table.record(conditions, self.frequency_hz),
+            None => false,
+        }
+    }
+
+    /// Frequency expected under `conditions`, corrected using the compensation
+    /// table
+    pub fn frequency_at<S: Storage>(
+        &self,
+        conditions: Option<Conditions>,
+        table: &Table<S>,
+    ) -> u32 {
+        match (self.conditions, conditions) {
+            (Some(measured), Some(conditions)) => {
+                table.correct(self.frequency_hz, measured, conditions)
+            }
+            _ => self.frequency_hz,
+        }
+    }
+
+    /// Plan the schedule of the next sleep period under `conditions`. If the
+    /// watchdog will be calibrated at the end of it, the period is shortened by
+    /// the time the calibration takes.
+    pub fn plan<S: Storage>(&self, conditions: Option<Conditions>, table: &Table<S>) -> Schedule {
+        let frequency_hz = self.frequency_at(conditions, table);
+        let awake_us = if self.remaining == 1 {
+            calibration_us(frequency_hz)
+        } else {
+            0
+        };
+        plan(self.period_us, frequency_hz, awake_us)
+    }
+
+    /// Plan the schedule of the next sleep period and count it towards the
+    /// next calibration. This should be called once per period, before the
+    /// previous one ends.
+    pub fn next_schedule<S: Storage>(
+        &mut self,
+        conditions: Option<Conditions>,
+        table: &Table<S>,
+    ) -> Schedule {
+        self.remaining = self.remaining.saturating_sub(1);
+        // The calibration at boot happens before anything is measured, so
+        // assume the conditions have not changed since
+        if self.conditions.is_none() {
+            self.conditions = conditions;
+        }
+        self.plan(conditions, table)
+    }
+}
@@ -0,0 +1,1212 @@
+extern crate std;
+
+use std::{string::String, vec, vec::Vec};
+
+use acurite_protocol::{pulse::Pulse, tx00606, tx592, DeciCelsius};
+
+use super::*;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Event {
+    ReadTemperature,
+    ReadBattery,
+    Transmit(Vec<Pulse>),
+    Led(bool),
+    Delay(u16),
+    Report(bool),
+    Sleep,
+}
+
+/// Board that records every operation
+struct MockBoard {
+    temperature: Result<DeciCelsius, ()>,
+    battery_mv: u16,
+    sensor_error_blinks: u8,
+    events: Vec<Event>,
+}
+
+impl MockBoard {
+    fn new(temperature: Result<DeciCelsius, ()>, battery_mv: u16) -> Self {
+        Self {
+            temperature,
+            battery_mv,
+            sensor_error_blinks: ERROR_BLINKS,
+            events: Vec::new(),
+        }
+    }
+}
+
+impl Board for MockBoard {
+    type SensorError = ();
+
+    fn read_temperature(&mut self) -> Result<DeciCelsius, ()> {
+        self.events.push(Event::ReadTemperature);
+        self.temperature
+    }
+
+    fn read_battery_mv(&mut self) -> u16 {
+        self.events.push(Event::ReadBattery);
+        self.battery_mv
+    }
+
+    fn transmit(&mut self, pulses: impl Iterator<Item = Pulse>) {
+        self.events.push(Event::Transmit(pulses.collect()));
+    }
+
+    fn set_led(&mut self, on: bool) {
+        self.events.push(Event::Led(on));
+    }
+
+    fn delay_ms(&mut self, ms: u16) {
+        self.events.push(Event::Delay(ms));
+    }
+
+    fn sleep(&mut self) {
+        self.events.push(Event::Sleep);
+    }
+
+    fn report<P: Protocol>(&mut self, result: &Result<Report, CycleError<(), P::Error>>) {
+        self.events.push(Event::Report(result.is_ok()));
+    }
+
+    fn error_blinks<P: Protocol>(&self, error: &CycleError<(), P::Error>) -> u8 {
+        match error {
+            CycleError::Sensor(()) => self.sensor_error_blinks,
+            CycleError::Encode(_) => ERROR_BLINKS,
+        }
+    }
+}
+
+const CONFIG: CycleConfig = CycleConfig {
+    battery_low_mv: 2000,
+    repeats: None,
+};
+
+fn error_blinks(blinks: u8) -> Vec<Event> {
+    let mut events = Vec::new();
+    for _ in 0..blinks {
+        events.extend([
+            Event::Delay(100),
+            Event::Led(true),
+            Event::Delay(100),
+            Event::Led(false),
+        ]);
+    }
+    events
+}
+
+#[test]
+fn test_cycle_transmit() {
+    let mut board = MockBoard::new(Ok(DeciCelsius::new(310)), 3000);
+    let mut protocol = protocol::Tx00606 { id: 0xe6 };
+
+    let result = run_cycle(&mut board, &mut protocol, &CONFIG);
+    assert_eq!(
+        Ok(Report {
+            temperature: DeciCelsius::new(310),
+            battery_mv: 3000
+        }),
+        result
+    );
+
+    let pulses: Vec<Pulse> = tx00606::Message::new(0xe6, true, DeciCelsius::new(310))
+        .unwrap()
+        .pulses()
+        .collect();
+    let mut expected = vec![
+        Event::Led(true),
+        Event::ReadTemperature,
+        Event::Led(false),
+        Event::ReadBattery,
+    ];
+    expected.extend((0..tx00606::REPEATS).map(|_| Event::Transmit(pulses.clone())));
+    expected.extend([Event::Report(true), Event::Sleep]);
+    assert_eq!(expected, board.events);
+}
+
+#[test]
+fn test_cycle_low_battery() {
+    let mut board = MockBoard::new(Ok(DeciCelsius::new(310)), 2000);
+    let mut protocol = protocol::Tx00606 { id: 0xe6 };
+
+    run_cycle(&mut board, &mut protocol, &CONFIG).unwrap();
+
+    let pulses: Vec<Pulse> = tx00606::Message::new(0xe6, false, DeciCelsius::new(310))
+        .unwrap()
+        .pulses()
+        .collect();
+    assert!(board.events.contains(&Event::Transmit(pulses)));
+}
+
+#[test]
+fn test_cycle_repeats() {
+    let mut board = MockBoard::new(Ok(DeciCelsius::new(310)), 3000);
+    let mut protocol = protocol::Tx00606 { id: 0xe6 };
+    let config = CycleConfig {
+        repeats: Some(2),
+        ..CONFIG
+    };
+
+    run_cycle(&mut board, &mut protocol, &config).unwrap();
+    let transmissions = board
+        .events
+        .iter()
+        .filter(|e| matches!(e, Event::Transmit(_)))
+        .count();
+    assert_eq!(2, transmissions);
+}
+
+#[test]
+fn test_sensor_without_sleep() {
+    let mut board = MockBoard::new(Ok(DeciCelsius::new(310)), 3000);
+    let mut protocols = [
+        protocol::Tx00606 { id: 0xe6 },
+        protocol::Tx00606 { id: 0xe7 },
+    ];
+
+    for protocol in &mut protocols {
+        run_sensor(&mut board, protocol, &CONFIG).unwrap();
+    }
+    for id in [0xe6, 0xe7] {
+        let pulses: Vec<Pulse> = tx00606::Message::new(id, true, DeciCelsius::new(310))
+            .unwrap()
+            .pulses()
+            .collect();
+        assert!(board.events.contains(&Event::Transmit(pulses)));
+    }
+    assert!(!board.events.contains(&Event::Sleep));
+}
+
+#[test]
+fn test_cycle_sensor_error() {
+    let mut board = MockBoard::new(Err(()), 3000);
+    let mut protocol = protocol::Tx00606 { id: 0xe6 };
+
+    let result = run_cycle(&mut board, &mut protocol, &CONFIG);
+    assert_eq!(Err(CycleError::Sensor(())), result);
+
+    let mut expected = vec![
+        Event::Led(true),
+        Event::ReadTemperature,
+        Event::Led(false),
+        Event::Report(false),
+    ];
+    expected.extend(error_blinks(ERROR_BLINKS));
+    expected.push(Event::Sleep);
+    assert_eq!(expected, board.events);
+}
+
+#[test]
+fn test_cycle_error_blinks() {
+    let mut board = MockBoard::new(Err(()), 3000);
+    board.sensor_error_blinks = 2;
+    let mut protocol = protocol::Tx00606 { id: 0xe6 };
+
+    run_cycle(&mut board, &mut protocol, &CONFIG).unwrap_err();
+    let mut expected = vec![
+        Event::Led(true),
+        Event::ReadTemperature,
+        Event::Led(false),
+        Event::Report(false),
+    ];
+    expected.extend(error_blinks(2));
+    expected.push(Event::Sleep);
+    assert_eq!(expected, board.events);
+}
+
+#[test]
+fn test_cycle_encode_error() {
+    let mut board = MockBoard::new(Ok(DeciCelsius::new(2048)), 3000);
+    let mut protocol = protocol::Tx00606 { id: 0xe6 };
+
+    let result = run_cycle(&mut board, &mut protocol, &CONFIG);
+    assert_eq!(
+        Err(CycleError::Encode(tx00606::Error::TemperatureRange(
+            DeciCelsius::new(2048)
+        ))),
+        result
+    );
+
+    let mut expected = vec![
+        Event::Led(true),
+        Event::ReadTemperature,
+        Event::Led(false),
+        Event::ReadBattery,
+        Event::Report(false),
+    ];
+    expected.extend(error_blinks(ERROR_BLINKS));
+    expected.push(Event::Sleep);
+    assert_eq!(expected, board.events);
+}
+
+#[test]
+fn test_cycle_592txr_sequence() {
+    let mut board = MockBoard::new(Ok(DeciCelsius::new(231)), 3000);
+    let mut protocol = protocol::Tx592::new(tx592::Channel::A, 0x123, 45);
+
+    for sequence in [0, 1, 2, 3, 0] {
+        board.events.clear();
+        run_cycle(&mut board, &mut protocol, &CONFIG).unwrap();
+
+        let pulses: Vec<Pulse> = tx592::Message::new(
+            tx592::Channel::A,
+            sequence,
+            0x123,
+            true,
+            45,
+            DeciCelsius::new(231),
+        )
+        .unwrap()
+        .pulses()
+        .collect();
+        let transmissions = board
+            .events
+            .iter()
+            .filter(|e| **e == Event::Transmit(pulses.clone()))
+            .count();
+        assert_eq!(tx592::REPEATS, transmissions);
+    }
+}
+
+#[test]
+fn test_sleep_timeout_cycles() {
+    // Timeouts are named for the nominal watchdog frequency
+    assert_eq!(2048, sleep::Timeout::Ms16.cycles());
+    assert_eq!(1048576, sleep::Timeout::Ms8000.cycles());
+    assert_eq!(9, sleep::Timeout::Ms8000.prescaler());
+}
+
+#[test]
+fn test_sleep_schedule() {
+    use sleep::Timeout::*;
+
+    let schedule = sleep::plan(31_000_000, 133_220, 0);
+    assert_eq!(
+        &[Ms8000, Ms8000, Ms8000, Ms4000, Ms2000, Ms1000, Ms500, Ms16],
+        schedule.timeouts()
+    );
+    assert_eq!(4130816, schedule.cycles());
+
+    // Never empty
+    assert_eq!(&[Ms16], sleep::Schedule::new(0).timeouts());
+    assert_eq!(&[Ms16], sleep::Schedule::new(1024).timeouts());
+    assert_eq!(&[Ms16], sleep::Schedule::new(1025).timeouts());
+    assert_eq!(&[Ms32], sleep::Schedule::new(4096).timeouts());
+    // Exactly half way between 3 and 4 units, 4 units is shorter
+    assert_eq!(&[Ms64], sleep::Schedule::new(3 * 2048 + 1024).timeouts());
+    // Exactly half way between 4 and 5 units, 4 units is shorter
+    assert_eq!(&[Ms64], sleep::Schedule::new(4 * 2048 + 1024).timeouts());
+
+    // Too long schedules are truncated
+    assert_eq!(
+        sleep::MAX_SCHEDULE_LEN,
+        sleep::Schedule::new(u32::MAX).timeouts().len()
+    );
+}
+
+#[test]
+fn test_sleep_schedule_shortest() {
+    const UNIT: u32 = 2048;
+    const MAX_UNITS: usize = 16 * 512;
+
+    // Length of the shortest sequence for each number of units, found by
+    // dynamic programming over all timeouts
+    let mut shortest = vec![usize::MAX; MAX_UNITS + 1];
+    shortest[0] = 0;
+    for units in 1..=MAX_UNITS {
+        for prescaler in 0..10 {
+            let timeout = 1 << prescaler;
+            if timeout <= units && shortest[units - timeout] != usize::MAX {
+                shortest[units] = shortest[units].min(shortest[units - timeout] + 1);
+            }
+        }
+    }
+
+    for (units, &len) in shortest.iter().enumerate().skip(1) {
+        if len > sleep::MAX_SCHEDULE_LEN {
+            continue;
+        }
+        let schedule = sleep::Schedule::new(units as u32 * UNIT);
+        assert_eq!(units as u32 * UNIT, schedule.cycles(), "units: {units}");
+        assert_eq!(len, schedule.timeouts().len(), "units: {units}");
+        // Longest timeouts first
+        assert!(schedule.timeouts().windows(2).all(|w| w[0] >= w[1]));
+    }
+}
+
+#[test]
+fn test_sleep_schedule_rounding() {
+    const UNIT: u32 = 2048;
+
+    // Anything shorter is rounded up to the shortest timeout
+    for cycles in UNIT / 2..64 * UNIT {
+        let schedule = sleep::Schedule::new(cycles);
+        let error = schedule.cycles().abs_diff(cycles);
+        assert!(error <= UNIT / 2, "cycles: {cycles}");
+        if error == UNIT / 2 {
+            let other = sleep::Schedule::new(if schedule.cycles() > cycles {
+                cycles - UNIT / 2
+            } else {
+                cycles + UNIT / 2
+            });
+            assert!(schedule.timeouts().len() <= other.timeouts().len());
+        }
+    }
+}
+
+#[test]
+fn test_sleep_plan() {
+    for frequency_hz in sleep::CALIBRATION_RANGE_HZ.0..=sleep::CALIBRATION_RANGE_HZ.1 {
+        for awake_us in [0, sleep::calibration_us(frequency_hz), 1_000_000] {
+            let schedule = sleep::plan(31_000_000, frequency_hz, awake_us);
+            assert!(schedule.timeouts().len() < sleep::MAX_SCHEDULE_LEN);
+            // Within half of the shortest timeout, plus rounding to the nearest
+            // cycle
+            let sleep_us = schedule.cycles() as f64 * 1e6 / frequency_hz as f64;
+            let error_us = (sleep_us - (31_000_000 - awake_us) as f64).abs();
+            let max_error_us = 1025.0 * 1e6 / frequency_hz as f64;
+            assert!(error_us <= max_error_us, "frequency: {frequency_hz}");
+        }
+    }
+    // Awake for as long as or longer than the period, so there is nothing left
+    // to sleep, but the watchdog still has to be started
+    for awake_us in [1000, 2000, u32::MAX] {
+        assert_eq!(
+            &[sleep::Timeout::Ms16],
+            sleep::plan(1000, 128_000, awake_us).timeouts()
+        );
+    }
+}
+
+#[test]
+fn test_sleep_calibrated_frequency() {
+    // 64 ms at 128 kHz is 64000 cycles at 1 MHz
+    assert_eq!(
+        Some(128_000),
+        sleep::calibrated_frequency_hz(64_000, 1_000_000)
+    );
+    assert_eq!(
+        Some(133_220),
+        sleep::calibrated_frequency_hz(61_492, 1_000_000)
+    );
+    assert_eq!(None, sleep::calibrated_frequency_hz(0, 1_000_000));
+    assert_eq!(None, sleep::calibrated_frequency_hz(10_000, 1_000_000));
+    assert_eq!(None, sleep::calibrated_frequency_hz(200_000, 1_000_000));
+}
+
+#[test]
+fn test_sleep_calibration() {
+    let table = compensation::Table::new();
+    let mut calibration = sleep::Calibration::new(3);
+    assert!(calibration.due());
+
+    assert!(calibration.update(61_492, 1_000_000, None));
+    assert_eq!(133_220, calibration.frequency_hz());
+    let schedule = sleep::plan(31_000_000, 133_220, 0);
+    let compensated = sleep::plan(31_000_000, 133_220, sleep::calibration_us(133_220));
+
+    // The period that ends with a calibration is shortened
+    assert_eq!(schedule, calibration.plan(None, &table));
+    assert_eq!(schedule, calibration.next_schedule(None, &table));
+    assert!(!calibration.due());
+    assert_eq!(compensated, calibration.next_schedule(None, &table));
+    assert!(!calibration.due());
+    assert_eq!(schedule, calibration.next_schedule(None, &table));
+    assert!(calibration.due());
+
+    // Implausible measurements keep the previous frequency
+    assert!(!calibration.update(0, 1_000_000, None));
+    assert_eq!(133_220, calibration.frequency_hz());
+    assert!(!calibration.due());
+}
+
+const ROOM: compensation::Conditions = compensation::Conditions {
+    temperature: DeciCelsius::new(200),
+    battery_mv: 3000,
+};
+
+const COLD: compensation::Conditions = compensation::Conditions {
+    temperature: DeciCelsius::new(-200),
+    battery_mv: 3000,
+};
+
+#[test]
+fn test_sleep_calibration_compensated() {
+    let mut table = compensation::Table::new();
+    table.record(ROOM, 128_000);
+    table.record(COLD, 124_000);
+
+    let mut calibration = sleep::Calibration::new(20);
+    calibration.update(61_492, 1_000_000, Some(ROOM));
+    assert_eq!(133_220, calibration.frequency_at(Some(ROOM), &table));
+    assert_eq!(133_220, calibration.frequency_at(None, &table));
+    // The watchdog slows down by the same ratio as in the table
+    assert_eq!(129_056, calibration.frequency_at(Some(COLD), &table));
+    assert_eq!(
+        sleep::plan(31_000_000, 129_056, 0),
+        calibration.next_schedule(Some(COLD), &table)
+    );
+}
+
+#[test]
+fn test_sleep_calibration_record() {
+    let mut table = compensation::Table::new();
+    let mut calibration = sleep::Calibration::new(1);
+    calibration.update(64_000, 1_000_000, Some(COLD));
+    assert!(calibration.record(&mut table));
+    assert_eq!(Some(128_000), table.frequency_hz(COLD));
+    // Same measurement does not change the table
+    assert!(!calibration.record(&mut table));
+    // Not recorded without conditions
+    calibration.update(60_000, 1_000_000, None);
+    assert!(!calibration.record(&mut table));
+    assert_eq!(Some(128_000), table.frequency_hz(COLD));
+}
+
+#[test]
+fn test_sleep_calibration_boot() {
+    let mut table = compensation::Table::new();
+    table.record(ROOM, 128_000);
+    table.record(COLD, 124_000);
+
+    // Calibrated before the conditions are known
+    let mut calibration = sleep::Calibration::new(20);
+    calibration.update(64_000, 1_000_000, None);
+    assert_eq!(
+        sleep::plan(31_000_000, 128_000, 0),
+        calibration.next_schedule(Some(ROOM), &table)
+    );
+    assert_eq!(124_000, calibration.frequency_at(Some(COLD), &table));
+}
+
+#[test]
+fn test_sleep_calibration_period() {
+    let mut calibration = sleep::Calibration::new(20).period_ms(10_000);
+    calibration.update(64_000, 1_000_000, None);
+    assert_eq!(
+        sleep::plan(10_000_000, 128_000, 0),
+        calibration.next_schedule(None, &compensation::Table::new())
+    );
+}
+
+#[test]
+fn test_compensation_lookup() {
+    use compensation::{Conditions, Table};
+
+    let mut table = Table::new();
+    assert!(table.is_empty());
+    assert_eq!(None, table.frequency_hz(ROOM));
+
+    // Only one measurement, which is used everywhere
+    assert!(table.record(ROOM, 128_000));
+    assert!(!table.is_empty());
+    assert_eq!(Some(128_000), table.frequency_hz(COLD));
+    assert_eq!(
+        Some(128_000),
+        table.frequency_hz(Conditions {
+            temperature: DeciCelsius::new(800),
+            battery_mv: 1800,
+        })
+    );
+
+    // Interpolated between the closest measured temperatures
+    table.record(COLD, 124_000);
+    assert_eq!(Some(124_000), table.frequency_hz(COLD));
+    assert_eq!(
+        Some(126_000),
+        table.frequency_hz(Conditions {
+            temperature: DeciCelsius::new(0),
+            battery_mv: 3000,
+        })
+    );
+    // Clamped outside the measured range
+    assert_eq!(
+        Some(124_000),
+        table.frequency_hz(Conditions {
+            temperature: DeciCelsius::new(-400),
+            battery_mv: 3100,
+        })
+    );
+
+    // The closest row with measurements is used
+    table.record(
+        Conditions {
+            temperature: DeciCelsius::new(250),
+            battery_mv: 2100,
+        },
+        132_000,
+    );
+    assert_eq!(
+        Some(132_000),
+        table.frequency_hz(Conditions {
+            temperature: DeciCelsius::new(0),
+            battery_mv: 2000,
+        })
+    );
+    assert_eq!(Some(124_000), table.frequency_hz(COLD));
+}
+
+#[test]
+fn test_compensation_record_average() {
+    let mut table = compensation::Table::new();
+    table.record(ROOM, 128_000);
+    assert!(table.record(ROOM, 130_000));
+    assert_eq!(Some(129_000), table.frequency_hz(ROOM));
+}
+
+#[test]
+fn test_compensation_correct() {
+    let mut table = compensation::Table::new();
+    assert_eq!(133_000, table.correct(133_000, ROOM, COLD));
+    table.record(ROOM, 128_000);
+    table.record(COLD, 120_000);
+    assert_eq!(124_687, table.correct(133_000, ROOM, COLD));
+    assert_eq!(133_000, table.correct(124_687, COLD, ROOM) + 1);
+}
+
+#[test]
+fn test_compensation_storage() {
+    use compensation::{Table, SIZE};
+
+    let mut table = Table::new();
+    table.record(ROOM, 128_000);
+    table.record(COLD, 124_000);
+    let mut bytes = table.clone().into_storage();
+    assert_eq!(table, Table::open(bytes));
+
+    assert!(!Table::new().is_valid());
+    assert!(table.is_valid());
+    // Erased EEPROM
+    assert!(!Table::open([0xff; SIZE]).is_valid());
+    // Corrupted
+    bytes[10] ^= 0x01;
+    let mut corrupted = Table::open(bytes);
+    assert!(!corrupted.is_valid());
+    assert!(corrupted.is_empty());
+    // The first measurement replaces a corrupted table
+    assert!(corrupted.record(ROOM, 128_000));
+    assert_eq!(Some(128_000), corrupted.frequency_hz(COLD));
+    assert_eq!(corrupted, Table::open(corrupted.clone().into_storage()));
+}
+
+/// Storage that counts writes, like EEPROM wear
+struct CountingStorage {
+    bytes: [u8; compensation::SIZE],
+    writes: usize,
+}
+
+impl compensation::Storage for CountingStorage {
+    fn read(&self, index: usize) -> u8 {
+        self.bytes[index]
+    }
+
+    fn write(&mut self, index: usize, value: u8) {
+        self.bytes[index] = value;
+        self.writes += 1;
+    }
+}
+
+#[test]
+fn test_compensation_record_writes() {
+    use compensation::{Table, SIZE};
+
+    let mut table = Table::open(CountingStorage {
+        bytes: [0xff; SIZE],
+        writes: 0,
+    });
+    // The table is cleared before the first cell is written
+    assert!(table.record(ROOM, 128_000));
+    let storage = table.into_storage();
+    assert_eq!(SIZE - 1 + 3, storage.writes);
+
+    // After that, only the cell and CRC
+    let mut table = Table::open_known(
+        CountingStorage {
+            writes: 0,
+            ..storage
+        },
+        true,
+    );
+    assert!(table.is_valid());
+    assert!(table.record(COLD, 124_000));
+    assert!(!table.record(COLD, 124_000));
+    assert_eq!(3, table.into_storage().writes);
+}
+
+const CUSTOM: config::Config = config::Config {
+    id: 0xe6,
+    battery_low_mv: 2200,
+    repeats: Some(3),
+    period_ms: 10_000,
+    sensor_config: 0x8170,
+    temperature_offset: DeciCelsius::new(-15),
+};
+
+#[test]
+fn test_config_default() {
+    let config = config::Config::default();
+    assert_eq!(sleep::PERIOD_MS, config.period_ms);
+    assert_eq!(
+        CycleConfig {
+            battery_low_mv: 2000,
+            repeats: None,
+        },
+        config.cycle()
+    );
+}
+
+#[test]
+fn test_config_serialize() {
+    use config::Config;
+
+    assert_eq!(
+        Some(Config::DEFAULT),
+        Config::from_bytes(&Config::DEFAULT.to_bytes())
+    );
+    let mut bytes = CUSTOM.to_bytes();
+    assert_eq!(Some(CUSTOM), Config::from_bytes(&bytes));
+    // Space reserved after the configuration is ignored
+    let mut reserved = [0xff; 32];
+    reserved[..Config::SIZE].copy_from_slice(&bytes);
+    assert_eq!(Some(CUSTOM), Config::from_bytes(&reserved));
+
+    // Erased EEPROM
+    assert_eq!(None, Config::from_bytes(&[0xff; Config::SIZE]));
+    // Truncated
+    assert_eq!(None, Config::from_bytes(&bytes[..Config::SIZE - 1]));
+    assert_eq!(None, Config::from_bytes(&[]));
+    // Corrupted
+    bytes[4] ^= 0x01;
+    assert_eq!(None, Config::from_bytes(&bytes));
+}
+
+#[test]
+fn test_config_version() {
+    use acurite_protocol::crc8;
+    use config::Config;
+
+    // Written by newer firmware with an unknown layout
+    let mut bytes = CUSTOM.to_bytes();
+    bytes[1] = config::VERSION + 1;
+    bytes[Config::SIZE - 1] = crc8(&bytes[..Config::SIZE - 1], 0x07, 0);
+    assert_eq!(None, Config::from_bytes(&bytes));
+
+    let mut bytes = CUSTOM.to_bytes();
+    bytes[1] = 0;
+    bytes[Config::SIZE - 1] = crc8(&bytes[..Config::SIZE - 1], 0x07, 0);
+    assert_eq!(None, Config::from_bytes(&bytes));
+}
+
+#[test]
+fn test_config_clamp() {
+    use config::Config;
+
+    // Out of range fields are clamped, keeping the ID
+    let bytes = Config {
+        period_ms: sleep::PERIOD_MS + 1,
+        temperature_offset: DeciCelsius::new(-101),
+        ..CUSTOM
+    }
+    .to_bytes();
+    assert_eq!(
+        Some(Config {
+            period_ms: sleep::PERIOD_MS,
+            temperature_offset: DeciCelsius::new(-100),
+            ..CUSTOM
+        }),
+        Config::from_bytes(&bytes)
+    );
+    let bytes = Config {
+        period_ms: 0,
+        temperature_offset: DeciCelsius::new(i16::MAX),
+        ..CUSTOM
+    }
+    .to_bytes();
+    assert_eq!(
+        Some(Config {
+            period_ms: 1_000,
+            temperature_offset: DeciCelsius::new(100),
+            ..CUSTOM
+        }),
+        Config::from_bytes(&bytes)
+    );
+}
+
+#[test]
+fn test_config_migrate() {
+    use acurite_protocol::crc8;
+    use config::Config;
+
+    // Version 1 didn't have the temperature offset
+    let mut bytes = CUSTOM.to_bytes()[..12].to_vec();
+    bytes[1] = 1;
+    bytes.push(crc8(&bytes, 0x07, 0));
+    assert_eq!(
+        Some(Config {
+            temperature_offset: DeciCelsius::new(0),
+            ..CUSTOM
+        }),
+        Config::from_bytes(&bytes)
+    );
+}
+
+#[test]
+fn test_console_parse() {
+    use console::{Command, Error, Setting};
+
+    assert_eq!(Ok(Command::Get(None)), Command::parse("get"));
+    assert_eq!(
+        Ok(Command::Get(Some(Setting::Battery))),
+        Command::parse("  get   battery ")
+    );
+    assert_eq!(
+        Ok(Command::Set(Setting::Id, 230)),
+        Command::parse("set id 230")
+    );
+    assert_eq!(
+        Ok(Command::Set(Setting::Repeats, 0)),
+        Command::parse("set repeats 0")
+    );
+    assert_eq!(Ok(Command::Measure), Command::parse("measure"));
+    assert_eq!(Ok(Command::Stats), Command::parse("stats"));
+    assert_eq!(Ok(Command::Defaults), Command::parse("defaults"));
+    assert_eq!(Ok(Command::Help), Command::parse("help"));
+
+    assert_eq!(Err(Error::UnknownCommand), Command::parse(""));
+    assert_eq!(Err(Error::UnknownCommand), Command::parse("reboot"));
+    assert_eq!(Err(Error::UnknownSetting), Command::parse("get humidity"));
+    assert_eq!(Err(Error::MissingArgument), Command::parse("set"));
+    assert_eq!(Err(Error::MissingArgument), Command::parse("set id"));
+    assert_eq!(Err(Error::UnexpectedArgument), Command::parse("stats now"));
+    assert_eq!(Err(Error::UnexpectedArgument), Command::parse("set id 1 2"));
+    assert_eq!(Err(Error::InvalidValue), Command::parse("set id x"));
+    assert_eq!(Err(Error::OutOfRange), Command::parse("set id 256"));
+    assert_eq!(Err(Error::OutOfRange), Command::parse("set id -1"));
+    assert_eq!(Err(Error::OutOfRange), Command::parse("set repeats 21"));
+}
+
+#[test]
+fn test_console_offset() {
+    use console::{Command, Error, Setting};
+
+    let offset = |value| Command::parse(&std::format!("set offset {value}"));
+    assert_eq!(Ok(Command::Set(Setting::Offset, 15)), offset("1.5"));
+    assert_eq!(Ok(Command::Set(Setting::Offset, -5)), offset("-0.5"));
+    assert_eq!(Ok(Command::Set(Setting::Offset, -20)), offset("-2"));
+    assert_eq!(Ok(Command::Set(Setting::Offset, 100)), offset("10.0"));
+    assert_eq!(Err(Error::OutOfRange), offset("10.1"));
+    assert_eq!(Err(Error::InvalidValue), offset("0.25"));
+    assert_eq!(Err(Error::InvalidValue), offset(".5"));
+    assert_eq!(Err(Error::InvalidValue), offset("1."));
+    assert_eq!(Err(Error::InvalidValue), offset("--1"));
+    assert_eq!(Err(Error::InvalidValue), offset("+1"));
+}
+
+#[test]
+fn test_console_settings() {
+    use console::{Error, Setting};
+
+    let mut config = config::Config::DEFAULT;
+    for (setting, value) in [
+        (Setting::Id, 230),
+        (Setting::Battery, 2200),
+        (Setting::Offset, -15),
+        (Setting::Repeats, 3),
+    ] {
+        setting.set(&mut config, value).unwrap();
+        assert_eq!(value, setting.get(&config));
+    }
+    assert_eq!(
+        config::Config {
+            period_ms: sleep::PERIOD_MS,
+            sensor_config: config::DEFAULT_SENSOR_CONFIG,
+            ..CUSTOM
+        },
+        config
+    );
+
+    Setting::Repeats.set(&mut config, 0).unwrap();
+    assert_eq!(None, config.repeats);
+    assert_eq!(
+        Err(Error::OutOfRange),
+        Setting::Battery.set(&mut config, 70_000)
+    );
+    assert_eq!(2200, config.battery_low_mv);
+}
+
+#[test]
+fn test_console_line_buffer() {
+    use console::{Error, LineBuffer};
+
+    fn feed<const N: usize>(
+        buffer: &mut LineBuffer<N>,
+        bytes: &[u8],
+    ) -> Vec<Result<String, Error>> {
+        bytes
+            .iter()
+            .filter_map(|b| buffer.push(*b).map(|line| line.map(String::from)))
+            .collect()
+    }
+
+    let mut buffer = LineBuffer::<8>::new();
+    assert_eq!(
+        vec![Ok(String::from("get id")), Ok(String::from("stats"))],
+        feed(&mut buffer, b"get id\r\n\r\nstats\n")
+    );
+    // Backspace
+    assert_eq!(
+        vec![Ok(String::from("help"))],
+        feed(&mut buffer, b"helq\x7fp\r")
+    );
+    // Too long, and the buffer recovers on the next line
+    assert_eq!(
+        vec![Err(Error::LineTooLong), Ok(String::from("measure"))],
+        feed(&mut buffer, b"get battery\rmeasure\r")
+    );
+}
+
+#[test]
+fn test_stats() {
+    let mut stats = Stats::default();
+    let report = Report {
+        temperature: DeciCelsius::new(215),
+        battery_mv: 2900,
+    };
+    stats.record::<(), ()>(&Ok(report));
+    stats.record::<(), ()>(&Err(CycleError::Sensor(())));
+    stats.record::<(), ()>(&Err(CycleError::Encode(())));
+    stats.record::<(), ()>(&Err(CycleError::Sensor(())));
+    assert_eq!(
+        Stats {
+            cycles: 4,
+            sensor_errors: 2,
+            encode_errors: 1,
+            last: Some(report),
+        },
+        stats
+    );
+}
+
+#[test]
+fn test_tmp102_extended_above_128() {
+    use tmp102::{Mode, Reading};
+
+    // Register values from the datasheet, with the extended mode flag
+    for (register, tenths) in [
+        (0x4b01, 1500), // 150
+        (0x4b09, 1501), // 150.0625
+        (0x4801, 1440), // 144
+        (0x4001, 1280), // 128
+        (0x3ff9, 1279), // 127.9375
+    ] {
+        let reading = Reading::from_register(register);
+        assert_eq!(Mode::Extended, reading.mode());
+        assert_eq!(DeciCelsius::new(tenths), reading.temperature());
+    }
+    assert_eq!(2400, Reading::from_register(0x4b01).sixteenths());
+    assert!(Reading::from_register(0x4b01).is_plausible());
+    assert!(!Reading::from_register(0x4b11).is_plausible());
+    assert!(!Mode::Normal
+        .range()
+        .contains(&Reading::from_register(0x4001).sixteenths()));
+    assert!(Mode::Extended
+        .range()
+        .contains(&Reading::from_register(0x4001).sixteenths()));
+}
+
+#[test]
+fn test_tmp102_normal() {
+    use tmp102::{Mode, Reading};
+
+    for (register, tenths) in [
+        (0x7ff0, 1279),           // 127.9375
+        (0x0040, 3),              // 0.25
+        (0x0000, 0),              // 0
+        (0xffc0u16 as i16, -3),   // -0.25
+        (0xc900u16 as i16, -550), // -55
+    ] {
+        let reading = Reading::from_register(register);
+        assert_eq!(Mode::Normal, reading.mode());
+        assert_eq!(DeciCelsius::new(tenths), reading.temperature());
+    }
+    // Not specified, but can be reported by a faulty sensor
+    assert!(!Reading::from_register(0xc8f0u16 as i16).is_plausible());
+    assert!(Reading::from_register(0xc900u16 as i16).is_plausible());
+}
+
+#[test]
+fn test_tmp102_round_trip() {
+    use tmp102::Reading;
+
+    for tenths in -550..=1500 {
+        let temperature = DeciCelsius::new(tenths);
+        let register = temperature.to_tmp102_extended().unwrap();
+        assert_eq!(temperature, Reading::from_register(register).temperature());
+        if let Some(register) = temperature.to_tmp102() {
+            assert_eq!(temperature, Reading::from_register(register).temperature());
+        }
+    }
+}
+
+#[test]
+fn test_tmp102_config_default() {
+    use tmp102::{Config, Mode};
+    // One-shot, shutdown and extended mode
+    let config = Config::OS | Config::SD | Config::EM;
+    assert_eq!(config::DEFAULT_SENSOR_CONFIG, config.bits());
+    assert_eq!(config, Config::from_bytes([0x81, 0x10]));
+    assert_eq!(Mode::Extended, config.mode());
+    assert_eq!(Mode::Normal, config.difference(Config::EM).mode());
+}
+
+#[test]
+fn test_tmp102_config_fields() {
+    use tmp102::{Config, ConversionRate, FaultQueue, Polarity, ThermostatMode};
+    let config = Config::OS | Config::EM;
+
+    assert_eq!(
+        0b00 << 11,
+        Config::empty().fault_queue(FaultQueue::One).bits()
+    );
+    assert_eq!(
+        0b11 << 11,
+        Config::empty().fault_queue(FaultQueue::Six).bits()
+    );
+    // Setting a field replaces its previous value and keeps the other bits
+    assert_eq!(
+        config | Config::F1,
+        config
+            .fault_queue(FaultQueue::Six)
+            .fault_queue(FaultQueue::Four)
+    );
+    assert_eq!(config | Config::F2, config.fault_queue(FaultQueue::Two));
+
+    assert_eq!(
+        0b11 << 6,
+        Config::empty().conversion_rate(ConversionRate::Hz8).bits()
+    );
+    assert_eq!(
+        config | Config::CR0,
+        config
+            .conversion_rate(ConversionRate::Hz8)
+            .conversion_rate(ConversionRate::Hz1)
+    );
+    assert_eq!(
+        config | Config::CR1,
+        config.conversion_rate(ConversionRate::Hz4)
+    );
+    assert_eq!(config, config.conversion_rate(ConversionRate::Hz0_25));
+
+    let high = config.polarity(Polarity::ActiveHigh);
+    assert_eq!(config | Config::POL, high);
+    assert_eq!(config, high.polarity(Polarity::ActiveLow));
+
+    let interrupt = config.thermostat_mode(ThermostatMode::Interrupt);
+    assert_eq!(config | Config::TM, interrupt);
+    assert_eq!(
+        config,
+        interrupt.thermostat_mode(ThermostatMode::Comparator)
+    );
+}
+
+#[test]
+fn test_tmp102_config_alert() {
+    use tmp102::{Config, Polarity};
+    // AL reads as the inactive level of the ALERT pin when there is no alert,
+    // so the polarity also inverts it
+    let low = Config::EM.polarity(Polarity::ActiveLow);
+    assert!(!low.union(Config::AL).alert());
+    assert!(low.alert());
+    let high = Config::EM.polarity(Polarity::ActiveHigh);
+    assert!(high.union(Config::AL).alert());
+    assert!(!high.alert());
+}
+
+#[test]
+fn test_tmp102_limit() {
+    use tmp102::{Mode, Reading};
+    let d = DeciCelsius::new;
+
+    // Power on defaults of T_LOW and T_HIGH from the datasheet
+    let low = Reading::from_limit_register(0x4b00, Mode::Normal);
+    assert_eq!(d(750), low.temperature());
+    let high = Reading::from_limit_register(0x5000, Mode::Normal);
+    assert_eq!(d(800), high.temperature());
+    assert_eq!(1280, high.sixteenths());
+    // Extended mode shifts by one bit less, and has no mode flag
+    let high = Reading::from_limit_register(0x2800, Mode::Extended);
+    assert_eq!(Mode::Extended, high.mode());
+    assert_eq!(d(800), high.temperature());
+
+    for (tenths, normal, extended) in [
+        (-250, Some(0xe700u16 as i16), 0xf380u16 as i16),
+        (0, Some(0), 0),
+        (750, Some(0x4b00), 0x2580),
+        (1500, None, 0x4b00),
+    ] {
+        assert_eq!(
+            normal,
+            Reading::from_temperature(d(tenths), Mode::Normal).map(Reading::to_limit_register)
+        );
+        let reading = Reading::from_temperature(d(tenths), Mode::Extended).unwrap();
+        assert_eq!(Mode::Extended, reading.mode());
+        assert_eq!(extended, reading.to_limit_register());
+    }
+    assert_eq!(None, Reading::from_temperature(d(2560), Mode::Extended));
+
+    for tenths in -550..=1500 {
+        for mode in [Mode::Normal, Mode::Extended] {
+            if let Some(reading) = Reading::from_temperature(d(tenths), mode) {
+                assert_eq!(d(tenths), reading.temperature());
+                let register = reading.to_limit_register();
+                assert_eq!(reading, Reading::from_limit_register(register, mode));
+            }
+        }
+    }
+}
+
+#[test]
+fn test_tmp117_temperature() {
+    let d = DeciCelsius::new;
+    // Register values from the datasheet
+    assert_eq!(d(1500), tmp117::temperature(0x4b00)); // 150
+    assert_eq!(d(1000), tmp117::temperature(0x3200)); // 100
+    assert_eq!(d(0), tmp117::temperature(0x0001)); // 0.0078125
+    assert_eq!(d(0), tmp117::temperature(0x0000));
+    assert_eq!(d(0), tmp117::temperature(-1)); // -0.0078125
+    assert_eq!(d(-250), tmp117::temperature(0xf380u16 as i16)); // -25
+    assert_eq!(d(-550), tmp117::temperature(0xe480u16 as i16)); // -55
+    assert_eq!(d(-2560), tmp117::temperature(i16::MIN));
+    assert_eq!(d(0), tmp117::temperature(0x0006)); // 0.046875
+    assert_eq!(d(1), tmp117::temperature(0x0007)); // 0.0546875
+    assert_eq!(d(-1), tmp117::temperature(-7)); // -0.0546875
+                                                // Halfway between tenths, rounded away from zero
+    assert_eq!(d(3), tmp117::temperature(0x0020)); // 0.25
+    assert_eq!(d(-3), tmp117::temperature(-0x20)); // -0.25
+
+    assert!(tmp117::SPECIFIED_RANGE.contains(&0x4b00));
+    assert!(!tmp117::SPECIFIED_RANGE.contains(&0x4b01));
+    assert!(!tmp117::SPECIFIED_RANGE.contains(&i16::MIN));
+}
+
+#[test]
+fn test_tmp117_offset() {
+    let d = DeciCelsius::new;
+    assert_eq!(Some(0), tmp117::offset_register(d(0)));
+    assert_eq!(Some(13), tmp117::offset_register(d(1))); // 0.1015625
+    assert_eq!(Some(-13), tmp117::offset_register(d(-1)));
+    assert_eq!(Some(128), tmp117::offset_register(d(10)));
+    assert_eq!(Some(-1280), tmp117::offset_register(d(-100)));
+    assert_eq!(Some(i16::MIN), tmp117::offset_register(d(-2560)));
+    assert_eq!(None, tmp117::offset_register(d(2560)));
+    for tenths in -1000..=1000 {
+        let register = tmp117::offset_register(d(tenths)).unwrap();
+        assert_eq!(d(tenths), tmp117::temperature(register));
+    }
+}
+
+#[test]
+fn test_tmp117_config() {
+    use tmp117::{Averaging, Config, ConversionMode};
+
+    assert_eq!(
+        0x0c20,
+        Config::empty()
+            .conversion_mode(ConversionMode::OneShot)
+            .averaging(Averaging::X8)
+            .bits()
+    );
+    assert_eq!(
+        Config::MOD0 | Config::AVG1 | Config::AVG0,
+        Config::MOD1
+            .averaging(Averaging::X32)
+            .conversion_mode(ConversionMode::Shutdown)
+            .averaging(Averaging::X64)
+    );
+    // Setting a field leaves the other bits alone
+    assert_eq!(
+        Config::all() - Config::MOD1 - Config::MOD0 - Config::AVG1 - Config::AVG0,
+        Config::all()
+            .conversion_mode(ConversionMode::Continuous)
+            .averaging(Averaging::None)
+    );
+    assert_eq!(
+        Config::HIGH_ALERT | Config::DATA_READY | Config::AVG0,
+        Config::from_bytes([0xa0, 0x20])
+    );
+    assert_eq!(125, Averaging::X8.conversion_time_ms());
+}
+
+/// Sensor that finishes after a number of checks, recording each check and
+/// wait
+#[derive(Default)]
+struct MockPoll {
+    ready_after: usize,
+    checks: usize,
+    waits: usize,
+}
+
+fn mock_poll(mock: &mut MockPoll, max_polls: u8) -> Result<(), poll::PollError<()>> {
+    poll::poll(
+        mock,
+        max_polls,
+        |m| {
+            m.checks += 1;
+            Ok(m.checks > m.ready_after)
+        },
+        |m| m.waits += 1,
+    )
+}
+
+#[test]
+fn test_poll_ready() {
+    let mut mock = MockPoll::default();
+    assert_eq!(Ok(()), mock_poll(&mut mock, 10));
+    assert_eq!((1, 0), (mock.checks, mock.waits));
+
+    let mut mock = MockPoll {
+        ready_after: 9,
+        ..Default::default()
+    };
+    assert_eq!(Ok(()), mock_poll(&mut mock, 10));
+    assert_eq!((10, 9), (mock.checks, mock.waits));
+}
+
+#[test]
+fn test_poll_timeout() {
+    let mut mock = MockPoll {
+        ready_after: 10,
+        ..Default::default()
+    };
+    assert_eq!(Err(poll::PollError::Timeout), mock_poll(&mut mock, 10));
+    assert_eq!((10, 9), (mock.checks, mock.waits));
+
+    // Always checks at least once
+    let mut mock = MockPoll::default();
+    assert_eq!(Ok(()), mock_poll(&mut mock, 0));
+    let mut mock = MockPoll {
+        ready_after: 1,
+        ..Default::default()
+    };
+    assert_eq!(Err(poll::PollError::Timeout), mock_poll(&mut mock, 0));
+    assert_eq!((1, 0), (mock.checks, mock.waits));
+}
+
+#[test]
+fn test_poll_error() {
+    let mut checks = 0;
+    let result = poll::poll(
+        &mut checks,
+        10,
+        |checks| {
+            *checks += 1;
+            if *checks == 3 {
+                Err(*checks)
+            } else {
+                Ok(false)
+            }
+        },
+        |_| {},
+    );
+    assert_eq!(Err(poll::PollError::Check(3)), result);
+}
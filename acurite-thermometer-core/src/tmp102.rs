@@ -0,0 +1,223 @@
+//! Decoding of TMP102 temperature readings and the configuration register
+//!
+//! The temperature register is left justified, with a resolution of 0.0625 °C
+//! in both modes. Normal mode uses 12 bits, covering -128 to 127.9375 °C, and
+//! extended mode uses 13 bits, covering -256 to 255.9375 °C. Bit 0 is set if
+//! the conversion was done in extended mode.
+
+use core::ops::RangeInclusive;
+
+use acurite_protocol::DeciCelsius;
+use bitflags::bitflags;
+
+/// Range the sensor is specified to measure, in sixteenths of a degree.
+/// Readings outside it are probably caused by a fault.
+pub const SPECIFIED_RANGE: RangeInclusive<i16> = -55 * 16..=150 * 16;
+
+/// Resolution and range of a conversion
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// 12-bit, up to 127.9375 °C
+    Normal,
+    /// 13-bit, up to 255.9375 °C
+    Extended,
+}
+
+impl Mode {
+    /// Range of temperatures that can be expressed, in sixteenths of a degree
+    pub const fn range(self) -> RangeInclusive<i16> {
+        match self {
+            Self::Normal => -0x800..=0x7ff,
+            Self::Extended => -0x1000..=0xfff,
+        }
+    }
+}
+
+/// A decoded temperature register
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Reading {
+    mode: Mode,
+    sixteenths: i16,
+}
+
+impl Reading {
+    /// Decode a temperature register, using the flag in bit 0 to tell which
+    /// mode the conversion was done in
+    pub const fn from_register(register: i16) -> Self {
+        if register & 1 != 0 {
+            Self {
+                mode: Mode::Extended,
+                sixteenths: register >> 3,
+            }
+        } else {
+            Self {
+                mode: Mode::Normal,
+                sixteenths: register >> 4,
+            }
+        }
+    }
+
+    /// Decode an alert limit register. Unlike the temperature register, it has
+    /// no mode flag, so the mode set in the configuration must be given.
+    pub const fn from_limit_register(register: i16, mode: Mode) -> Self {
+        let sixteenths = match mode {
+            Mode::Normal => register >> 4,
+            Mode::Extended => register >> 3,
+        };
+        Self { mode, sixteenths }
+    }
+
+    /// Encode as an alert limit register, in the mode of the reading
+    pub const fn to_limit_register(self) -> i16 {
+        match self.mode {
+            Mode::Normal => self.sixteenths << 4,
+            Mode::Extended => self.sixteenths << 3,
+        }
+    }
+
+    /// The reading closest to `temperature` in `mode`, for example to use as an
+    /// alert limit, or `None` if it is out of range
+    pub const fn from_temperature(temperature: DeciCelsius, mode: Mode) -> Option<Self> {
+        let register = match mode {
+            Mode::Normal => temperature.to_tmp102(),
+            Mode::Extended => temperature.to_tmp102_extended(),
+        };
+        match register {
+            Some(register) => Some(Self::from_register(register)),
+            None => None,
+        }
+    }
+
+    pub const fn mode(self) -> Mode {
+        self.mode
+    }
+
+    /// Temperature in sixteenths of a degree, which is the full resolution
+    pub const fn sixteenths(self) -> i16 {
+        self.sixteenths
+    }
+
+    /// Temperature rounded to a tenth of a degree
+    pub const fn temperature(self) -> DeciCelsius {
+        match self.mode {
+            Mode::Normal => DeciCelsius::from_tmp102(self.sixteenths << 4),
+            Mode::Extended => DeciCelsius::from_tmp102_extended(self.sixteenths << 3),
+        }
+    }
+
+    /// Whether the temperature is within the range the sensor is specified to
+    /// measure
+    pub fn is_plausible(self) -> bool {
+        SPECIFIED_RANGE.contains(&self.sixteenths)
+    }
+}
+
+/// Alert limit register. The ALERT pin is activated when the temperature
+/// reaches T_HIGH, and deactivated at T_LOW in comparator mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    Low,
+    High,
+}
+
+bitflags! {
+    /// Configuration register
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    #[repr(transparent)]
+    pub struct Config: u16 {
+        const OS = 1 << 15;
+        const R1 = 1 << 14;
+        const R2 = 1 << 13;
+        const F1 = 1 << 12;
+        const F2 = 1 << 11;
+        const POL = 1 << 10;
+        const TM = 1 << 9;
+        const SD = 1 << 8;
+        const CR1 = 1 << 7;
+        const CR0 = 1 << 6;
+        const AL = 1 << 5;
+        const EM = 1 << 4;
+    }
+}
+
+/// Number of consecutive faults needed to trigger the alert
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum FaultQueue {
+    One = 0b00,
+    Two = 0b01,
+    Four = 0b10,
+    Six = 0b11,
+}
+
+/// Active level of the ALERT pin
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Polarity {
+    ActiveLow,
+    ActiveHigh,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThermostatMode {
+    /// The alert is active from T_HIGH until the temperature falls below T_LOW
+    Comparator,
+    /// The alert is active when T_HIGH or T_LOW is crossed, until a register
+    /// is read
+    Interrupt,
+}
+
+/// Conversion rate in continuous mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ConversionRate {
+    Hz0_25 = 0b00,
+    Hz1 = 0b01,
+    Hz4 = 0b10,
+    Hz8 = 0b11,
+}
+
+impl Config {
+    /// Decode the register as read over I2C, MSB first
+    pub const fn from_bytes(bytes: [u8; 2]) -> Self {
+        Self::from_bits_retain(u16::from_be_bytes(bytes))
+    }
+
+    pub const fn fault_queue(self, faults: FaultQueue) -> Self {
+        let bits = self.difference(Self::F1.union(Self::F2)).bits();
+        Self::from_bits_retain(bits | (faults as u16) << 11)
+    }
+
+    pub const fn polarity(self, polarity: Polarity) -> Self {
+        match polarity {
+            Polarity::ActiveLow => self.difference(Self::POL),
+            Polarity::ActiveHigh => self.union(Self::POL),
+        }
+    }
+
+    pub const fn thermostat_mode(self, mode: ThermostatMode) -> Self {
+        match mode {
+            ThermostatMode::Comparator => self.difference(Self::TM),
+            ThermostatMode::Interrupt => self.union(Self::TM),
+        }
+    }
+
+    pub const fn conversion_rate(self, rate: ConversionRate) -> Self {
+        let bits = self.difference(Self::CR1.union(Self::CR0)).bits();
+        Self::from_bits_retain(bits | (rate as u16) << 6)
+    }
+
+    /// Resolution and range of conversions
+    pub const fn mode(self) -> Mode {
+        if self.contains(Self::EM) {
+            Mode::Extended
+        } else {
+            Mode::Normal
+        }
+    }
+
+    /// Whether the alert is active, according to the read-only AL bit
+    pub const fn alert(self) -> bool {
+        // POL also inverts AL
+        self.contains(Self::AL) == self.contains(Self::POL)
+    }
+}
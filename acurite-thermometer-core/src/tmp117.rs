@@ -0,0 +1,115 @@
+//! Conversion of TMP117 register values
+//!
+//! The temperature and offset registers are two's complement, with a
+//! resolution of 7.8125 m°C (1/128 °C).
+
+use core::ops::RangeInclusive;
+
+use acurite_protocol::DeciCelsius;
+use bitflags::bitflags;
+
+/// Contents of the device ID field, excluding the revision
+pub const DEVICE_ID: u16 = 0x117;
+
+/// Range the sensor is specified to measure, in register units. Readings
+/// outside it are probably caused by a fault.
+pub const SPECIFIED_RANGE: RangeInclusive<i16> = -55 * 128..=150 * 128;
+
+/// Convert a temperature register value, rounded to a tenth of a degree
+pub const fn temperature(register: i16) -> DeciCelsius {
+    // Rounding down to whole millidegrees can't change the rounded result,
+    // since the halfway points between tenths are whole millidegrees
+    let millidegrees = (register as i32 * 125) >> 4;
+    match DeciCelsius::from_millidegrees(millidegrees) {
+        Some(temperature) => temperature,
+        // The register can't exceed ±256 °C
+        None => unreachable!(),
+    }
+}
+
+/// Convert a temperature offset to an offset register value, or `None` if it
+/// is outside of the ±256 °C range
+pub const fn offset_register(offset: DeciCelsius) -> Option<i16> {
+    // 12.8 register units per tenth, rounded to the nearest
+    let scaled = offset.tenths() as i32 * 64;
+    let register = if scaled < 0 {
+        (scaled - 2) / 5
+    } else {
+        (scaled + 2) / 5
+    };
+    if register < i16::MIN as i32 || register > i16::MAX as i32 {
+        return None;
+    }
+    Some(register as i16)
+}
+
+bitflags! {
+    /// Configuration register
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    #[repr(transparent)]
+    pub struct Config: u16 {
+        const HIGH_ALERT = 1 << 15;
+        const LOW_ALERT = 1 << 14;
+        const DATA_READY = 1 << 13;
+        const EEPROM_BUSY = 1 << 12;
+        const MOD1 = 1 << 11;
+        const MOD0 = 1 << 10;
+        const CONV2 = 1 << 9;
+        const CONV1 = 1 << 8;
+        const CONV0 = 1 << 7;
+        const AVG1 = 1 << 6;
+        const AVG0 = 1 << 5;
+        const TNA = 1 << 4;
+        const POL = 1 << 3;
+        const DR_ALERT = 1 << 2;
+        const SOFT_RESET = 1 << 1;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ConversionMode {
+    Continuous = 0b00,
+    Shutdown = 0b01,
+    /// Convert once, then shut down
+    OneShot = 0b11,
+}
+
+/// Number of conversions averaged into each result
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Averaging {
+    None = 0b00,
+    X8 = 0b01,
+    X32 = 0b10,
+    X64 = 0b11,
+}
+
+impl Averaging {
+    /// Time taken by a one-shot conversion
+    pub const fn conversion_time_ms(self) -> u16 {
+        match self {
+            Self::None => 16,
+            Self::X8 => 125,
+            Self::X32 => 500,
+            Self::X64 => 1000,
+        }
+    }
+}
+
+impl Config {
+    /// Decode the register as read over I2C, MSB first
+    pub const fn from_bytes(bytes: [u8; 2]) -> Self {
+        Self::from_bits_retain(u16::from_be_bytes(bytes))
+    }
+
+    pub const fn conversion_mode(self, mode: ConversionMode) -> Self {
+        let bits = self.difference(Self::MOD1.union(Self::MOD0)).bits();
+        Self::from_bits_retain(bits | (mode as u16) << 10)
+    }
+
+    pub const fn averaging(self, averaging: Averaging) -> Self {
+        let bits = self.difference(Self::AVG1.union(Self::AVG0)).bits();
+        Self::from_bits_retain(bits | (averaging as u16) << 5)
+    }
+}
@@ -0,0 +1,160 @@
+use acurite_protocol::{pulse::Pulse, DeciCelsius};
+#[cfg(feature = "debug-uart")]
+use acurite_thermometer_core::Report;
+#[cfg(feature = "atmega328p")]
+use acurite_thermometer_core::Stats;
+use acurite_thermometer_core::{
+    compensation::Conditions, config::Config, Board, CycleError, Protocol, ERROR_BLINKS,
+};
+use hal::{
+    port::{mode::Output, Pin, PinOps},
+    prelude::*,
+};
+
+use super::{
+    adc, hal,
+    radio::Radio,
+    read_battery_mv,
+    sensor::{Address, TemperatureSensor},
+    timing::SleepTimer,
+    Delay, Sensor,
+};
+
+/// Thermometer hardware
+pub struct AvrBoard<L, R, T> {
+    pub cpu: hal::pac::CPU,
+    pub adc: adc::Adc,
+    pub timer: T,
+    /// Conditions measured during the last cycle
+    pub conditions: Option<Conditions>,
+    pub temperature: Option<DeciCelsius>,
+    pub eeprom: hal::Eeprom,
+    /// Driver for all sensors on the bus, addressing the selected one
+    pub sensor: Sensor,
+    /// Addresses of the sensors found at boot
+    pub sensors: [Address; 4],
+    pub sensor_count: usize,
+    pub config: Config,
+    pub radio: Radio<R, Delay>,
+    pub led: Pin<Output, L>,
+    #[cfg(feature = "debug-uart")]
+    pub uart: super::Uart,
+    #[cfg(feature = "atmega328p")]
+    pub stats: Stats,
+}
+
+impl<L: PinOps, R: PinOps, T> AvrBoard<L, R, T> {
+    /// Find the sensors that respond on the bus. If there are none, the default
+    /// address is kept so that the error is reported.
+    pub fn find_sensors(&mut self) {
+        self.sensor_count = 0;
+        for address in Address::ALL {
+            self.sensor.set_address(address);
+            // A sensor that answers but fails to measure is still used, so that
+            // its errors are reported
+            if self.sensor.probe(&self.config) {
+                self.sensors[self.sensor_count] = address;
+                self.sensor_count += 1;
+            }
+        }
+        if self.sensor_count == 0 {
+            self.sensors[0] = Address::Gnd;
+            self.sensor_count = 1;
+        }
+        self.select_sensor(0);
+    }
+
+    /// Measure using the sensor at `index` in [`Self::sensors`]
+    pub fn select_sensor(&mut self, index: usize) {
+        self.sensor.set_address(self.sensors[index]);
+    }
+}
+
+impl<L: PinOps, R: PinOps, T: SleepTimer> Board for AvrBoard<L, R, T> {
+    type SensorError = <Sensor as TemperatureSensor>::Error;
+
+    fn read_temperature(&mut self) -> Result<DeciCelsius, Self::SensorError> {
+        let temperature = self.sensor.measure(&self.config).map(|t| {
+            if Sensor::STORES_OFFSET {
+                t
+            } else {
+                t.checked_add(self.config.temperature_offset).unwrap_or(t)
+            }
+        });
+        // The sensor at 0x48 is on the board, so it is used to compensate the
+        // watchdog. Without it, the watchdog is not compensated.
+        if self.sensor.address() == Address::Gnd {
+            self.temperature = temperature.ok();
+        }
+        temperature
+    }
+
+    fn read_battery_mv(&mut self) -> u16 {
+        let battery_mv = read_battery_mv(&mut self.adc, &self.cpu);
+        self.conditions = self.temperature.map(|temperature| Conditions {
+            temperature,
+            battery_mv,
+        });
+        battery_mv
+    }
+
+    fn transmit(&mut self, pulses: impl Iterator<Item = Pulse>) {
+        self.radio.transmit(pulses);
+    }
+
+    fn set_led(&mut self, on: bool) {
+        // Active low
+        if on {
+            self.led.set_low();
+        } else {
+            self.led.set_high();
+        }
+    }
+
+    fn delay_ms(&mut self, ms: u16) {
+        Delay::new().delay_ms(ms);
+    }
+
+    fn sleep(&mut self) {
+        self.timer.plan(self.conditions, &mut self.eeprom);
+        #[cfg(feature = "atmega328p")]
+        if super::console::take_request() {
+            self.console();
+        }
+
+        self.adc.enable(false);
+        self.timer.sleep(&self.cpu, &mut self.eeprom);
+        self.adc.enable(true);
+    }
+
+    #[cfg(feature = "debug-uart")]
+    fn report<P: Protocol>(
+        &mut self,
+        result: &Result<Report, CycleError<Self::SensorError, P::Error>>,
+    ) {
+        #[cfg(feature = "atmega328p")]
+        self.stats.record(result);
+        match result {
+            Ok(report) => ufmt::uwriteln!(
+                &mut self.uart,
+                "temp: {}, batt: {}",
+                report.temperature,
+                report.battery_mv
+            ),
+            Err(CycleError::Sensor(error)) => {
+                ufmt::uwriteln!(&mut self.uart, "error: {}", Sensor::error_message(error))
+            }
+            Err(CycleError::Encode(_)) => {
+                ufmt::uwriteln!(&mut self.uart, "error: temperature out of range")
+            }
+        }
+        .void_unwrap();
+    }
+
+    fn error_blinks<P: Protocol>(&self, error: &CycleError<Self::SensorError, P::Error>) -> u8 {
+        match error {
+            CycleError::Sensor(error) => Sensor::error_blinks(error),
+            CycleError::Encode(_) => ERROR_BLINKS,
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use acurite_thermometer_core::sleep;
+
+use super::{hal, watchdog};
+
+/// Interrupt-only mode, so that the timeout can be polled without resetting the
+/// MCU
+const CALIBRATION_CONFIG: watchdog::Config = watchdog::Config::new()
+    .timeout(sleep::CALIBRATION_TIMEOUT)
+    .interrupt();
+
+/// Timer1 running from the CPU clock
+pub struct Timer {
+    tc1: hal::pac::TC1,
+    /// TIFR is shared between both timers
+    #[cfg(feature = "attiny85")]
+    tc0: hal::pac::TC0,
+}
+
+#[cfg(feature = "atmega328p")]
+impl Timer {
+    /// Width of the counter
+    const BITS: u32 = 16;
+    const PRESCALER: u32 = 1;
+
+    pub fn new(tc1: hal::pac::TC1) -> Self {
+        Self { tc1 }
+    }
+
+    fn start(&mut self) {
+        self.tc1.tccr1a.reset();
+        self.tc1.tcnt1.write(|w| unsafe { w.bits(0) });
+        self.tc1.tifr1.write(|w| w.tov1().set_bit());
+        self.tc1.tccr1b.write(|w| w.cs1().direct());
+    }
+
+    fn stop(&mut self) {
+        self.tc1.tccr1b.reset();
+    }
+
+    fn count(&self) -> u16 {
+        self.tc1.tcnt1.read().bits()
+    }
+
+    /// Check and clear the overflow flag
+    fn overflowed(&mut self) -> bool {
+        let overflowed = self.tc1.tifr1.read().tov1().bit_is_set();
+        if overflowed {
+            self.tc1.tifr1.write(|w| w.tov1().set_bit());
+        }
+        overflowed
+    }
+}
+
+#[cfg(feature = "attiny85")]
+impl Timer {
+    /// Width of the counter
+    const BITS: u32 = 8;
+    /// Keeps the number of overflows low enough to reliably poll
+    const PRESCALER: u32 = 16;
+
+    pub fn new(tc0: hal::pac::TC0, tc1: hal::pac::TC1) -> Self {
+        Self { tc1, tc0 }
+    }
+
+    fn start(&mut self) {
+        self.tc1.tcnt1.write(|w| unsafe { w.bits(0) });
+        self.tc0.tifr.write(|w| w.tov1().set_bit());
+        // CS1 = CK/16
+        self.tc1.tccr1.write(|w| unsafe { w.bits(0b0101) });
+    }
+
+    fn stop(&mut self) {
+        self.tc1.tccr1.reset();
+    }
+
+    fn count(&self) -> u16 {
+        self.tc1.tcnt1.read().bits().into()
+    }
+
+    /// Check and clear the overflow flag
+    fn overflowed(&mut self) -> bool {
+        let overflowed = self.tc0.tifr.read().tov1().bit_is_set();
+        if overflowed {
+            self.tc0.tifr.write(|w| w.tov1().set_bit());
+        }
+        overflowed
+    }
+}
+
+/// Count CPU cycles during a [`sleep::CALIBRATION_TIMEOUT`] watchdog timeout.
+/// This leaves the watchdog in interrupt-only mode, so it must be reconfigured
+/// afterwards.
+pub fn measure(watchdog: &mut watchdog::Watchdog, timer: &mut Timer, cpu: &hal::pac::CPU) -> u32 {
+    cpu.prr.modify(|_, w| w.prtim1().clear_bit());
+
+    let cycles = avr_device::interrupt::free(|_| {
+        watchdog.configure(CALIBRATION_CONFIG);
+        watchdog.reset();
+        watchdog.clear_interrupt();
+
+        // Wait for the first timeout to synchronize with the watchdog
+        // oscillator
+        while !watchdog.interrupt_pending() {}
+        timer.start();
+        watchdog.clear_interrupt();
+
+        let mut overflows = 0;
+        while !watchdog.interrupt_pending() {
+            if timer.overflowed() {
+                overflows += 1;
+            }
+        }
+        let count = timer.count();
+        // The timer may have overflowed after it was last checked
+        if timer.overflowed() && count < 1 << (Timer::BITS - 1) {
+            overflows += 1;
+        }
+        timer.stop();
+        watchdog.clear_interrupt();
+
+        ((overflows << Timer::BITS) | count as u32) * Timer::PRESCALER
+    });
+
+    cpu.prr.modify(|_, w| w.prtim1().set_bit());
+    cycles
+}
@@ -0,0 +1,169 @@
+//! Serial configuration and diagnostics console. Any input on the RX pin opens
+//! the console after the next transmission, and it closes again after
+//! [`TIMEOUT_MS`] without input.
+
+use core::cell::Cell;
+
+use acurite_thermometer_core::{
+    config::Config,
+    console::{Command, LineBuffer, Setting, HELP},
+    Board,
+};
+use avr_device::interrupt::{self, Mutex};
+use hal::port::PinOps;
+use hal::prelude::*;
+
+use super::{
+    board::AvrBoard, eeprom, hal, sensor::TemperatureSensor, timing::SleepTimer, Delay, Sensor,
+};
+
+/// Time without input after which the console closes
+const TIMEOUT_MS: u16 = 30_000;
+const LINE_LENGTH: usize = 32;
+
+/// Set when activity on the RX pin requests the console
+static REQUESTED: Mutex<Cell<bool>> = Mutex::new(Cell::new(false));
+
+/// Wake the MCU on any change of the RX pin (PCINT16), since the USART doesn't
+/// run while sleeping
+pub fn enable_wake(exint: &hal::pac::EXINT) {
+    exint.pcmsk2.write(|w| unsafe { w.bits(1 << 0) });
+    exint
+        .pcicr
+        .modify(|r, w| unsafe { w.bits(r.bits() | 1 << 2) });
+}
+
+/// Called from the pin change interrupt
+pub fn request() {
+    interrupt::free(|cs| REQUESTED.borrow(cs).set(true));
+}
+
+/// Whether the console was requested since the last call
+pub fn take_request() -> bool {
+    interrupt::free(|cs| REQUESTED.borrow(cs).replace(false))
+}
+
+impl<L: PinOps, R: PinOps, T: SleepTimer> AvrBoard<L, R, T> {
+    /// Run the console until no input is received for [`TIMEOUT_MS`]. The MCU
+    /// stays awake, so the next transmission is delayed.
+    pub fn console(&mut self) {
+        let mut line = LineBuffer::<LINE_LENGTH>::new();
+        // The input that woke the MCU was lost
+        ufmt::uwrite!(&mut self.uart, "\r\n> ").void_unwrap();
+
+        let mut idle_ms = 0;
+        while idle_ms < TIMEOUT_MS {
+            let Ok(byte) = self.uart.read() else {
+                Delay::new().delay_ms(1u8);
+                idle_ms += 1;
+                continue;
+            };
+            idle_ms = 0;
+            self.echo(byte);
+            if let Some(line) = line.push(byte) {
+                match line.and_then(Command::parse) {
+                    Ok(command) => self.execute(command),
+                    Err(e) => {
+                        ufmt::uwriteln!(&mut self.uart, "error: {}", e.message()).void_unwrap()
+                    }
+                }
+                ufmt::uwrite!(&mut self.uart, "> ").void_unwrap();
+            }
+        }
+        ufmt::uwriteln!(&mut self.uart, "\r\nconsole closed").void_unwrap();
+        // Input received while closing shouldn't open it again
+        take_request();
+    }
+
+    fn echo(&mut self, byte: u8) {
+        match byte {
+            b'\r' | b'\n' => ufmt::uwrite!(&mut self.uart, "\r\n").void_unwrap(),
+            0x08 | 0x7f => ufmt::uwrite!(&mut self.uart, "\x08 \x08").void_unwrap(),
+            _ => self.uart.write_byte(byte),
+        }
+    }
+
+    fn execute(&mut self, command: Command) {
+        match command {
+            Command::Get(Some(setting)) => self.print_setting(setting),
+            Command::Get(None) => {
+                for setting in Setting::ALL {
+                    self.print_setting(setting);
+                }
+            }
+            Command::Set(setting, value) => {
+                // The value was checked while parsing
+                setting.set(&mut self.config, value).ok();
+                eeprom::write_config(&mut self.eeprom, &self.config);
+                // Sensors that add the offset themselves need to be programmed
+                // again
+                if setting == Setting::Offset && Sensor::STORES_OFFSET {
+                    self.find_sensors();
+                }
+                self.print_setting(setting);
+            }
+            Command::Measure => match self.read_temperature() {
+                Ok(temperature) => {
+                    let battery_mv = self.read_battery_mv();
+                    ufmt::uwriteln!(
+                        &mut self.uart,
+                        "temp: {}, batt: {}",
+                        temperature,
+                        battery_mv
+                    )
+                    .void_unwrap();
+                }
+                Err(_) => ufmt::uwriteln!(&mut self.uart, "error: failed to read temperature")
+                    .void_unwrap(),
+            },
+            Command::Stats => {
+                let stats = self.stats;
+                ufmt::uwriteln!(
+                    &mut self.uart,
+                    "cycles: {}, sensor errors: {}, encode errors: {}",
+                    stats.cycles,
+                    stats.sensor_errors,
+                    stats.encode_errors
+                )
+                .void_unwrap();
+                if let Some(last) = stats.last {
+                    ufmt::uwriteln!(
+                        &mut self.uart,
+                        "last: temp: {}, batt: {}",
+                        last.temperature,
+                        last.battery_mv
+                    )
+                    .void_unwrap();
+                }
+            }
+            Command::Defaults => {
+                // Keep the ID, so the display doesn't need to be paired again
+                self.config = Config {
+                    id: self.config.id,
+                    ..Config::DEFAULT
+                };
+                eeprom::write_config(&mut self.eeprom, &self.config);
+                ufmt::uwriteln!(&mut self.uart, "defaults restored").void_unwrap();
+            }
+            Command::Help => ufmt::uwriteln!(&mut self.uart, "{}", HELP).void_unwrap(),
+        }
+    }
+
+    fn print_setting(&mut self, setting: Setting) {
+        match setting {
+            Setting::Offset => ufmt::uwriteln!(
+                &mut self.uart,
+                "{} = {}",
+                setting.name(),
+                self.config.temperature_offset
+            ),
+            _ => ufmt::uwriteln!(
+                &mut self.uart,
+                "{} = {}",
+                setting.name(),
+                setting.get(&self.config)
+            ),
+        }
+        .void_unwrap();
+    }
+}
@@ -0,0 +1,87 @@
+//! Layout of the data stored in EEPROM
+
+use acurite_protocol::crc8;
+use acurite_thermometer_core::{compensation, config::Config};
+
+use super::hal;
+
+/// Location of the watchdog compensation table
+const COMPENSATION_OFFSET: u16 = 0;
+/// Location of the transmitter ID stored before it was part of the
+/// configuration
+const LEGACY_ID_OFFSET: u16 = COMPENSATION_OFFSET + compensation::SIZE as u16;
+/// Location of the configuration
+const CONFIG_OFFSET: u16 = LEGACY_ID_OFFSET + LEGACY_ID_SIZE as u16;
+/// Space reserved for the configuration, allowing the layout to grow
+const CONFIG_RESERVED: usize = 32;
+
+/// Identifies a stored ID
+const LEGACY_ID_MARKER: u8 = 0x5a;
+const CRC_POLY: u8 = 0x07;
+/// Marker, ID and CRC
+const LEGACY_ID_SIZE: usize = 3;
+
+const _: () = assert!(Config::SIZE <= CONFIG_RESERVED);
+
+fn read_legacy_id(eeprom: &hal::Eeprom) -> Option<u8> {
+    let mut bytes = [0; LEGACY_ID_SIZE];
+    eeprom.read(LEGACY_ID_OFFSET, &mut bytes).ok()?;
+    if bytes[0] != LEGACY_ID_MARKER
+        || crc8(&bytes[..LEGACY_ID_SIZE - 1], CRC_POLY, 0) != bytes[LEGACY_ID_SIZE - 1]
+    {
+        return None;
+    }
+    Some(bytes[1])
+}
+
+/// Read the stored configuration, returning `None` if no valid configuration
+/// is stored. An ID stored by older firmware is kept, with the default
+/// configuration.
+pub fn read_config(eeprom: &hal::Eeprom) -> Option<Config> {
+    let mut bytes = [0; CONFIG_RESERVED];
+    eeprom.read(CONFIG_OFFSET, &mut bytes).ok()?;
+    Config::from_bytes(&bytes).or_else(|| {
+        read_legacy_id(eeprom).map(|id| Config {
+            id,
+            ..Config::DEFAULT
+        })
+    })
+}
+
+pub fn write_config(eeprom: &mut hal::Eeprom, config: &Config) {
+    eeprom.write(CONFIG_OFFSET, &config.to_bytes()).ok();
+}
+
+/// The compensation table in EEPROM, which is read a cell at a time rather than
+/// copied to RAM
+#[cfg(not(feature = "crystal"))]
+pub struct Compensation<'a>(&'a mut hal::Eeprom);
+
+#[cfg(not(feature = "crystal"))]
+impl compensation::Storage for Compensation<'_> {
+    fn read(&self, index: usize) -> u8 {
+        let mut byte = [0];
+        self.0
+            .read(COMPENSATION_OFFSET + index as u16, &mut byte)
+            .ok();
+        byte[0]
+    }
+
+    fn write(&mut self, index: usize, value: u8) {
+        self.0
+            .write(COMPENSATION_OFFSET + index as u16, &[value])
+            .ok();
+    }
+}
+
+#[cfg(not(feature = "crystal"))]
+/// Open the compensation table, checking its CRC if `valid` is not yet known
+pub fn compensation_table(
+    eeprom: &mut hal::Eeprom,
+    valid: Option<bool>,
+) -> compensation::Table<Compensation<'_>> {
+    match valid {
+        Some(valid) => compensation::Table::open_known(Compensation(eeprom), valid),
+        None => compensation::Table::open(Compensation(eeprom)),
+    }
+}
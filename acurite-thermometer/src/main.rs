@@ -2,21 +2,46 @@
 #![no_main]
 #![feature(abi_avr_interrupt)]
 
+#[cfg(feature = "tx592")]
+use acurite_protocol::tx592;
+#[cfg(feature = "tx986")]
+use acurite_protocol::tx986;
+#[cfg(not(feature = "crystal"))]
+use acurite_thermometer_core::sleep;
+#[cfg(feature = "tmp117")]
+use acurite_thermometer_core::tmp117::Averaging;
+use acurite_thermometer_core::{protocol, Board};
 #[cfg(feature = "atmega328p")]
 pub use atmega_hal as hal;
 #[cfg(feature = "attiny85")]
 pub use attiny_hal as hal;
+use hal::port::Pin;
+use hal::prelude::*;
 #[cfg(feature = "atmega328p")]
 use hal::usart::BaudrateArduinoExt;
-use hal::{port::Pin, prelude::*};
 use panic_halt as _;
+use timing::SleepTimer;
 
 mod adc;
+mod board;
+#[cfg(not(feature = "crystal"))]
+mod calibration;
+#[cfg(feature = "atmega328p")]
+mod console;
+mod eeprom;
 #[cfg(feature = "attiny85")]
 mod i2c;
 mod power;
 mod radio;
+mod sensor;
+#[cfg(all(feature = "attiny85", feature = "debug-uart"))]
+mod soft_uart;
+mod timing;
+#[cfg(feature = "tmp102")]
 mod tmp102;
+#[cfg(feature = "tmp117")]
+mod tmp117;
+#[cfg_attr(feature = "crystal", allow(dead_code))]
 mod watchdog;
 
 #[cfg(feature = "atmega328p")]
@@ -33,34 +58,52 @@ type I2c = hal::i2c::I2c<Speed>;
 #[cfg(feature = "attiny85")]
 type I2c = i2c::I2c<hal::port::PB0, hal::port::PB2, Speed>;
 
-/// TMP102 config
-/// - One-shot
-/// - Shutdown
-/// - Extended mode
-const TMP102_CONFIG: tmp102::Config = tmp102::Config::OS
-    .union(tmp102::Config::SD)
-    .union(tmp102::Config::EM);
-
-const BATTERY_LOW_MV: u16 = 2000;
-
-const fn wdt_config(timeout: hal::wdt::Timeout) -> watchdog::Config {
-    watchdog::Config::new()
-        .enable()
-        .timeout(timeout)
-        .interrupt()
-}
+#[cfg(not(any(feature = "tmp102", feature = "tmp117")))]
+compile_error!("one of the tmp102 or tmp117 features must be enabled");
+#[cfg(all(feature = "tmp102", feature = "tmp117"))]
+compile_error!("only one of the tmp102 and tmp117 features can be enabled");
+
+#[cfg(feature = "tmp102")]
+type Sensor = tmp102::Tmp102<I2c, Delay>;
+#[cfg(feature = "tmp117")]
+type Sensor = tmp117::Tmp117<I2c, Delay>;
+
+/// Number of times to poll for the end of a conversion before reporting a
+/// timeout
+#[cfg(feature = "tmp102")]
+const SENSOR_MAX_POLLS: u8 = tmp102::DEFAULT_MAX_POLLS;
+#[cfg(feature = "tmp117")]
+const SENSOR_MAX_POLLS: u8 = tmp117::DEFAULT_MAX_POLLS;
+/// Number of conversions the TMP117 averages into each measurement. More
+/// averaging reduces noise, but keeps the sensor awake for longer.
+#[cfg(feature = "tmp117")]
+const TMP117_AVERAGING: Averaging = Averaging::X8;
 
-// Needs to be adjusted for each chip
-use hal::wdt::Timeout::*;
-const WDT_CONFIG_SEQ: [watchdog::Config; 7] = [
-    wdt_config(Ms8000), // 1048576
-    wdt_config(Ms8000), // 1048576
-    wdt_config(Ms8000), // 1048576
-    wdt_config(Ms4000), // 524288
-    wdt_config(Ms2000), // 262144
-    wdt_config(Ms1000), // 131072
-    wdt_config(Ms500),  // 65536
-]; // = 4128768 @ 133.22 kHz = 30.99 sec
+#[cfg(feature = "atmega328p")]
+type Uart = hal::usart::Usart0<Speed>;
+#[cfg(all(feature = "attiny85", feature = "debug-uart"))]
+type Uart = soft_uart::SoftUart<hal::port::PB3>;
+
+/// In calibration mode, the watchdog is calibrated every cycle and the
+/// measurements are recorded in the compensation table
+#[cfg(all(feature = "calibration-mode", not(feature = "crystal")))]
+const CALIBRATION_INTERVAL: u16 = 1;
+#[cfg(not(any(feature = "calibration-mode", feature = "crystal")))]
+const CALIBRATION_INTERVAL: u16 = sleep::CALIBRATION_INTERVAL;
+
+/// Channel reported by the 592TXR, which must match the display
+#[cfg(feature = "tx592")]
+const TX592_CHANNEL: tx592::Channel = tx592::Channel::A;
+/// Sensor number reported by the 986, which selects the reading it replaces on
+/// the display
+#[cfg(feature = "tx986")]
+const TX986_SENSOR: tx986::Sensor = tx986::Sensor::Freezer;
+/// There is no humidity sensor, so report a fixed value
+#[cfg(any(feature = "tx592", feature = "tx609"))]
+const HUMIDITY: u8 = 0;
+/// Longest time to wait for the random pin to be released after requesting a
+/// new ID, so that a pin strap left in place doesn't stop the thermometer
+const NEW_ID_RELEASE_MS: u16 = 5000;
 
 #[cfg(feature = "atmega328p")]
 avr_hal_generic::renamed_pins! {
@@ -115,11 +158,70 @@ fn ADC() {}
 
 #[cfg(feature = "attiny85")]
 #[avr_device::interrupt(attiny85)]
-fn WDT() {}
+fn WDT() {
+    timing::expire();
+}
 
 #[cfg(feature = "atmega328p")]
 #[avr_device::interrupt(atmega328p)]
-fn WDT() {}
+fn WDT() {
+    timing::expire();
+}
+
+#[cfg(feature = "crystal")]
+#[avr_device::interrupt(atmega328p)]
+fn TIMER2_COMPA() {
+    timing::expire();
+}
+
+#[cfg(feature = "atmega328p")]
+#[avr_device::interrupt(atmega328p)]
+fn PCINT2() {
+    console::request();
+}
+
+#[cfg(not(any(
+    feature = "tx00606",
+    feature = "tx592",
+    feature = "tx609",
+    feature = "tx986"
+)))]
+compile_error!("one of the tx00606, tx592, tx609 or tx986 features must be enabled");
+#[cfg(any(
+    all(feature = "tx00606", feature = "tx592"),
+    all(feature = "tx00606", feature = "tx609"),
+    all(feature = "tx00606", feature = "tx986"),
+    all(feature = "tx592", feature = "tx609"),
+    all(feature = "tx592", feature = "tx986"),
+    all(feature = "tx609", feature = "tx986")
+))]
+compile_error!("only one of the tx00606, tx592, tx609 and tx986 features can be enabled");
+
+#[cfg(feature = "tx00606")]
+fn new_protocol(id: u8) -> impl protocol::Protocol {
+    protocol::Tx00606 { id }
+}
+
+#[cfg(feature = "tx592")]
+fn new_protocol(id: u8) -> impl protocol::Protocol {
+    protocol::Tx592::new(TX592_CHANNEL, id.into(), HUMIDITY)
+}
+
+#[cfg(feature = "tx609")]
+fn new_protocol(id: u8) -> impl protocol::Protocol {
+    protocol::Tx609 {
+        id,
+        humidity: HUMIDITY,
+    }
+}
+
+#[cfg(feature = "tx986")]
+fn new_protocol(id: u8) -> impl protocol::Protocol {
+    protocol::Tx986 {
+        sensor: TX986_SENSOR,
+        id: id.into(),
+    }
+}
 
 #[hal::entry]
 fn main() -> ! {
@@ -128,14 +230,22 @@ fn main() -> ! {
     let dp = hal::Peripherals::take().unwrap();
     let pins = Pins::with_mcu_pins(hal::pins!(dp));
 
-    let mut led = pins.led.into_output();
+    let led = pins.led.into_output();
 
     // Set the CPU clock divider to match the configured speed
-    #[cfg(feature = "atmega328p")]
+    #[cfg(all(feature = "atmega328p", not(feature = "crystal")))]
     power::cpu_clock_divider::<hal::clock::MHz16, Speed>(&dp.CPU).unwrap();
+    // The 32.768 kHz crystal occupies the XTAL pins, so the CPU runs from the
+    // internal RC oscillator
+    #[cfg(feature = "crystal")]
+    power::cpu_clock_divider::<hal::clock::MHz8, Speed>(&dp.CPU).unwrap();
     power::disable_unused_hardware(&dp.CPU, &dp.AC);
 
-    let mut watchdog = watchdog::Watchdog::new(dp.WDT, &dp.CPU.mcusr);
+    #[cfg(not(feature = "crystal"))]
+    let watchdog = watchdog::Watchdog::new(dp.WDT, &dp.CPU.mcusr);
+    // Unused, but must be disabled in case it was left running before a reset
+    #[cfg(feature = "crystal")]
+    watchdog::Watchdog::new(dp.WDT, &dp.CPU.mcusr);
 
     // Custom ADC driver that allows the use of noise reduction mode
     let mut adc = adc::Adc::new(
@@ -148,9 +258,34 @@ fn main() -> ! {
     // Enable ADC interrupt for power-reduction mode
     adc.interrupt(true);
 
-    // Random transmitter ID included in each message
-    let id = random_u8(&mut adc, hal::pac::adc::admux::MUX_A::ADC3);
-    pins.random.into_pull_up_input();
+    let mut eeprom = hal::Eeprom::new(dp.EEPROM);
+
+    // Holding the random pin low at boot requests a new ID, which is
+    // generated once it is released, or after a timeout
+    let random = pins.random.into_pull_up_input();
+    Delay::new().delay_ms(1u8);
+    let new_id = random.is_low();
+    let mut waited_ms = 0;
+    while random.is_low() && waited_ms < NEW_ID_RELEASE_MS {
+        Delay::new().delay_ms(10u8);
+        waited_ms += 10;
+    }
+    let random = random.into_floating_input();
+
+    let stored = eeprom::read_config(&eeprom);
+    let mut config = stored.unwrap_or_default();
+    // Transmitter ID included in each message. It is generated randomly once
+    // and then kept, so that the display doesn't need to be paired again after
+    // changing the battery.
+    if stored.is_none() || new_id {
+        config.id = random_u8(&mut adc, hal::pac::adc::admux::MUX_A::ADC3);
+        eeprom::write_config(&mut eeprom, &config);
+    }
+    // The random pin is free for debug output on the ATtiny85
+    #[cfg(not(all(feature = "attiny85", feature = "debug-uart")))]
+    random.into_pull_up_input();
+    #[cfg(all(feature = "attiny85", feature = "debug-uart"))]
+    let mut uart = soft_uart::SoftUart::new(random.into_output());
 
     #[cfg(feature = "atmega328p")]
     let mut uart = hal::usart::Usart0::<Speed>::new(
@@ -159,6 +294,8 @@ fn main() -> ! {
         pins.uart_tx.into_output(),
         9600.into_baudrate(),
     );
+    #[cfg(feature = "atmega328p")]
+    console::enable_wake(&dp.EXINT);
 
     #[cfg(feature = "atmega328p")]
     let i2c_peripheral = dp.TWI;
@@ -166,79 +303,78 @@ fn main() -> ! {
     let i2c_peripheral = dp.USI;
     let i2c = I2c::with_external_pullup(i2c_peripheral, pins.i2c_sda, pins.i2c_scl, 20000);
 
-    let mut sensor = tmp102::Tmp102::new(i2c, Delay::new());
-    let mut radio = radio::Radio::new(pins.radio.into_output(), Delay::new());
+    #[cfg_attr(not(feature = "tmp117"), allow(unused_mut))]
+    let mut sensor = Sensor::new(i2c, Delay::new(), sensor::Address::Gnd, SENSOR_MAX_POLLS);
+    #[cfg(feature = "tmp117")]
+    sensor.set_averaging(TMP117_AVERAGING);
+    let radio = radio::Radio::new(pins.radio.into_output(), Delay::new());
 
     // The first ADC read seems to be bad, so discard it. Its not the bandgap,
     // since it still happens if you wait a long time.
     read_battery_mv(&mut adc, &dp.CPU);
-    // First TMP102 reading also seems to be bad? Only happens on the real
-    // board.
-    sensor.oneshot(TMP102_CONFIG).ok();
 
-    #[cfg(feature = "atmega328p")]
-    ufmt::uwriteln!(&mut uart, "Booted").void_unwrap();
-
-    // Start first watchdog period. This also enables the interrupt.
-    watchdog.configure(WDT_CONFIG_SEQ[0]);
+    #[cfg(not(feature = "crystal"))]
+    let timer = timing::WatchdogTimer::new(
+        watchdog,
+        #[cfg(feature = "atmega328p")]
+        calibration::Timer::new(dp.TC1),
+        #[cfg(feature = "attiny85")]
+        calibration::Timer::new(dp.TC0, dp.TC1),
+        sleep::Calibration::new(CALIBRATION_INTERVAL).period_ms(config.period_ms),
+    );
+    #[cfg(feature = "crystal")]
+    let timer = timing::Crystal::new(dp.TC2, config.period_ms);
+
+    let mut board = board::AvrBoard {
+        cpu: dp.CPU,
+        adc,
+        timer,
+        conditions: None,
+        temperature: None,
+        eeprom,
+        sensor,
+        sensors: sensor::Address::ALL,
+        sensor_count: 0,
+        config,
+        radio,
+        led,
+        #[cfg(feature = "debug-uart")]
+        uart,
+        #[cfg(feature = "atmega328p")]
+        stats: Default::default(),
+    };
+    board.find_sensors();
+
+    #[cfg(feature = "debug-uart")]
+    ufmt::uwriteln!(
+        &mut board.uart,
+        "Booted, id: {}, sensors: {}",
+        config.id,
+        board.sensor_count
+    )
+    .void_unwrap();
+
+    // Each sensor is sent with its own ID, starting from the configured one
+    let new_protocols =
+        |id: u8| core::array::from_fn::<_, 4, _>(|i| new_protocol(id.wrapping_add(i as u8)));
+    let mut id = config.id;
+    let mut protocols = new_protocols(id);
+
+    // Calibrate the watchdog if necessary and start the first period
+    board.timer.start(&board.cpu, &mut board.eeprom);
     loop {
-        // Active low
-        led.set_low();
-        if let Ok(temp_reg) = sensor.oneshot(TMP102_CONFIG) {
-            led.set_high();
-
-            let temp = acurite_protocol::tx00606::convert_temperature(temp_reg);
-
-            let battery_mv = read_battery_mv(&mut adc, &dp.CPU);
-
-            #[cfg(feature = "atmega328p")]
-            ufmt::uwriteln!(
-                &mut uart,
-                "id: {}, temp: {}, temp reg: {}, batt: {}",
-                id,
-                temp,
-                temp_reg,
-                battery_mv
-            )
-            .void_unwrap();
-
-            let message =
-                acurite_protocol::tx00606::generate(id, battery_mv > BATTERY_LOW_MV, temp);
-
-            for _ in 0..7 {
-                radio.transmit(message);
-            }
-        } else {
-            #[cfg(feature = "atmega328p")]
-            ufmt::uwriteln!(&mut uart, "error: failed to read temperature").void_unwrap();
-            led.set_high();
-            for _ in 0..4 {
-                Delay::new().delay_ms(100u8);
-                led.set_low();
-                Delay::new().delay_ms(100u8);
-                led.set_high();
-            }
+        // The configuration can be changed from the console
+        if board.config.id != id {
+            id = board.config.id;
+            protocols = new_protocols(id);
         }
-
-        adc.enable(false);
-        power::sleep_enable(&dp.CPU, power::SleepMode::PowerDown);
-
-        // Watchdog has already been started, so just sleep
-        power::disable_bod_in_sleep(&dp.CPU);
-        avr_device::asm::sleep();
-
-        // Sleep for the rest of the periods
-        for config in &WDT_CONFIG_SEQ[1..] {
-            // This also re-enables the interrupt
-            watchdog.configure(*config);
-
-            power::disable_bod_in_sleep(&dp.CPU);
-            avr_device::asm::sleep();
+        let config = board.config.cycle();
+        for (i, protocol) in protocols[..board.sensor_count].iter_mut().enumerate() {
+            board.select_sensor(i);
+            // Errors are reported by the board and signalled with the LED
+            acurite_thermometer_core::run_sensor(&mut board, protocol, &config).ok();
         }
-        // Restart watchdog immediately after waking to minimize lost cycles
-        watchdog.configure(WDT_CONFIG_SEQ[0]);
-
-        power::sleep_disable(&dp.CPU);
-        adc.enable(true);
+        board.select_sensor(0);
+        board.sleep();
     }
 }
@@ -4,6 +4,9 @@ pub enum SleepMode {
     Idle = 0b000,
     AdcNoiseReduction = 0b001,
     PowerDown = 0b010,
+    /// Power-down, but with Timer2 kept running from its asynchronous clock
+    #[cfg(feature = "atmega328p")]
+    PowerSave = 0b011,
 }
 
 #[cfg(feature = "atmega328p")]
@@ -1,3 +1,5 @@
+use acurite_protocol::pulse::Pulse;
+
 use super::hal;
 
 pub struct Radio<P, D> {
@@ -14,43 +16,16 @@ where
         Self { pin, delay }
     }
 
-    fn pulse(&mut self, on_us: u16, off_us: u16) {
+    fn pulse(&mut self, pulse: Pulse) {
         self.pin.set_high();
-        self.delay.delay_us(on_us);
+        self.delay.delay_us(pulse.mark_us);
         self.pin.set_low();
-        self.delay.delay_us(off_us);
-    }
-
-    fn start(&mut self) {
-        self.pulse(500, 9000);
-    }
-
-    fn zero(&mut self) {
-        self.pulse(500, 2000);
-    }
-
-    fn one(&mut self) {
-        self.pulse(500, 4000);
-    }
-
-    fn stop(&mut self) {
-        self.pulse(500, 500);
+        self.delay.delay_us(pulse.space_us);
     }
 
-    pub fn transmit<const N: usize>(&mut self, data: [u8; N]) {
-        self.start();
-        for byte in data.iter() {
-            let mut byte = *byte;
-            for _ in 0..8 {
-                let bit = byte & 0x80 != 0;
-                if bit {
-                    self.one();
-                } else {
-                    self.zero();
-                }
-                byte <<= 1;
-            }
+    pub fn transmit(&mut self, pulses: impl IntoIterator<Item = Pulse>) {
+        for pulse in pulses {
+            self.pulse(pulse);
         }
-        self.stop();
     }
 }
@@ -0,0 +1,45 @@
+use acurite_protocol::DeciCelsius;
+use acurite_thermometer_core::config::Config;
+
+/// I2C address, selected by the pin ADD0 is connected to. The TMP102 and
+/// TMP117 use the same addresses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Address {
+    Gnd = 0x48,
+    Vplus = 0x49,
+    Sda = 0x4a,
+    Scl = 0x4b,
+}
+
+impl Address {
+    pub const ALL: [Self; 4] = [Self::Gnd, Self::Vplus, Self::Sda, Self::Scl];
+}
+
+/// Temperature sensor on the I2C bus, selected with a cargo feature
+pub trait TemperatureSensor {
+    type Error;
+
+    /// Whether the sensor adds the configured offset to its measurements
+    /// itself, after [`Self::probe`] stored it in the sensor
+    const STORES_OFFSET: bool = false;
+
+    fn address(&self) -> Address;
+
+    /// Talk to a different sensor on the same bus
+    fn set_address(&mut self, address: Address);
+
+    /// Whether a sensor of this type answers at the current address. It may
+    /// still fail to measure.
+    fn probe(&mut self, config: &Config) -> bool;
+
+    /// Take a single measurement and shut down again. The configured offset is
+    /// not applied, unless [`Self::STORES_OFFSET`].
+    fn measure(&mut self, config: &Config) -> Result<DeciCelsius, Self::Error>;
+
+    /// Number of times the LED blinks for `error`, which differs for each kind
+    /// of error and from [`acurite_thermometer_core::ERROR_BLINKS`]
+    fn error_blinks(error: &Self::Error) -> u8;
+
+    fn error_message(error: &Self::Error) -> &'static str;
+}
@@ -0,0 +1,59 @@
+//! Transmit-only software UART for debug output, using 8 data bits, no parity
+//! and one stop bit
+
+use avr_device::interrupt;
+use hal::port::{mode::Output, Pin, PinOps};
+use hal::prelude::*;
+
+use super::{hal, Delay};
+
+/// Low enough that the loop overhead is negligible at 1 MHz
+pub const BAUD_RATE: u32 = 1200;
+const BIT_US: u16 = (1_000_000 / BAUD_RATE) as u16;
+
+pub struct SoftUart<P> {
+    pin: Pin<Output, P>,
+    delay: Delay,
+}
+
+impl<P: PinOps> SoftUart<P> {
+    pub fn new(mut pin: Pin<Output, P>) -> Self {
+        // Idle high
+        pin.set_high();
+        Self {
+            pin,
+            delay: Delay::new(),
+        }
+    }
+
+    pub fn write_byte(&mut self, byte: u8) {
+        // Interrupts would stretch the bits
+        interrupt::free(|_| {
+            // Start bit
+            self.pin.set_low();
+            self.delay.delay_us(BIT_US);
+            for i in 0..8 {
+                if byte & (1 << i) != 0 {
+                    self.pin.set_high();
+                } else {
+                    self.pin.set_low();
+                }
+                self.delay.delay_us(BIT_US);
+            }
+            // Stop bit
+            self.pin.set_high();
+            self.delay.delay_us(BIT_US);
+        });
+    }
+}
+
+impl<P: PinOps> ufmt::uWrite for SoftUart<P> {
+    type Error = void::Void;
+
+    fn write_str(&mut self, s: &str) -> Result<(), Self::Error> {
+        for byte in s.bytes() {
+            self.write_byte(byte);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,256 @@
+use core::cell::Cell;
+
+use acurite_thermometer_core::compensation::Conditions;
+use acurite_thermometer_core::sleep;
+#[cfg(not(feature = "crystal"))]
+use acurite_thermometer_core::sleep::{Calibration, Schedule};
+use avr_device::interrupt::{self, CriticalSection, Mutex};
+#[cfg(not(feature = "crystal"))]
+use hal::clock::Clock;
+
+#[cfg(not(feature = "crystal"))]
+use super::{calibration, eeprom, watchdog, Speed};
+use super::{hal, power};
+
+/// Timer that wakes the MCU at the end of each transmit period
+pub trait SleepTimer {
+    /// Start the first period after boot. The EEPROM holds the compensation
+    /// table, if the timer uses one.
+    fn start(&mut self, cpu: &hal::pac::CPU, eeprom: &mut hal::Eeprom);
+
+    /// Prepare the next period while awake, using the conditions measured
+    /// during this cycle
+    fn plan(&mut self, conditions: Option<Conditions>, eeprom: &mut hal::Eeprom);
+
+    /// Sleep until the end of the current period. The next period starts
+    /// immediately on waking.
+    fn sleep(&mut self, cpu: &hal::pac::CPU, eeprom: &mut hal::Eeprom);
+}
+
+/// Set by the timer interrupt. Other interrupts can also wake the MCU, so this
+/// is used to tell them apart.
+static EXPIRED: Mutex<Cell<bool>> = Mutex::new(Cell::new(false));
+
+/// Called from the timer interrupt
+pub fn expire() {
+    interrupt::free(|cs| EXPIRED.borrow(cs).set(true));
+}
+
+/// Sleep until the timer interrupt fires, or return immediately if it already
+/// fired while awake. If the MCU stays awake for longer than a timer interval,
+/// for example while the console is in use, the period is extended.
+fn sleep_until_expired(cpu: &hal::pac::CPU) {
+    loop {
+        interrupt::disable();
+        // SAFETY: interrupts are disabled
+        if EXPIRED
+            .borrow(unsafe { CriticalSection::new() })
+            .replace(false)
+        {
+            unsafe { interrupt::enable() };
+            return;
+        }
+        power::disable_bod_in_sleep(cpu);
+        // The instruction after SEI is always executed before any pending
+        // interrupt, so an interrupt can't be missed before sleeping
+        unsafe { interrupt::enable() };
+        avr_device::asm::sleep();
+    }
+}
+
+#[cfg(not(feature = "crystal"))]
+const fn wdt_config(timeout: sleep::Timeout) -> watchdog::Config {
+    watchdog::Config::new()
+        .enable()
+        .timeout(timeout)
+        .interrupt()
+}
+
+/// Sleep using the calibrated watchdog oscillator
+#[cfg(not(feature = "crystal"))]
+pub struct WatchdogTimer {
+    watchdog: watchdog::Watchdog,
+    timer: calibration::Timer,
+    calibration: Calibration,
+    /// Schedule of the current sleep period
+    schedule: Schedule,
+    /// Schedule of the next sleep period, planned before going to sleep
+    next_schedule: Schedule,
+    /// Conditions measured during the last cycle
+    conditions: Option<Conditions>,
+    /// Whether the EEPROM holds a valid compensation table. Checking reads the
+    /// whole table, so it is only done once.
+    table_valid: Option<bool>,
+}
+
+#[cfg(not(feature = "crystal"))]
+impl WatchdogTimer {
+    pub fn new(
+        watchdog: watchdog::Watchdog,
+        timer: calibration::Timer,
+        calibration: Calibration,
+    ) -> Self {
+        Self {
+            watchdog,
+            timer,
+            calibration,
+            schedule: Schedule::new(0),
+            next_schedule: Schedule::new(0),
+            conditions: None,
+            table_valid: None,
+        }
+    }
+
+    /// Start the first watchdog period of the next sleep, calibrating the
+    /// watchdog first if necessary
+    fn start_watchdog(&mut self, cpu: &hal::pac::CPU, eeprom: &mut hal::Eeprom) {
+        if self.calibration.due() {
+            let cpu_cycles = calibration::measure(&mut self.watchdog, &mut self.timer, cpu);
+            let mut table = eeprom::compensation_table(eeprom, self.table_valid);
+            if self
+                .calibration
+                .update(cpu_cycles, Speed::FREQ, self.conditions)
+                && cfg!(feature = "calibration-mode")
+            {
+                self.calibration.record(&mut table);
+            }
+            self.schedule = self.calibration.plan(self.conditions, &table);
+            self.table_valid = Some(table.is_valid());
+        } else {
+            self.schedule = self.next_schedule;
+        }
+        // This also enables the interrupt. Schedules are never empty.
+        self.watchdog
+            .configure(wdt_config(self.schedule.timeouts()[0]));
+    }
+}
+
+#[cfg(not(feature = "crystal"))]
+impl SleepTimer for WatchdogTimer {
+    fn start(&mut self, cpu: &hal::pac::CPU, eeprom: &mut hal::Eeprom) {
+        self.start_watchdog(cpu, eeprom);
+    }
+
+    fn plan(&mut self, conditions: Option<Conditions>, eeprom: &mut hal::Eeprom) {
+        self.conditions = conditions;
+        // Plan while awake, so the watchdog can be restarted immediately after
+        // waking
+        let table = eeprom::compensation_table(eeprom, self.table_valid);
+        self.next_schedule = self.calibration.next_schedule(conditions, &table);
+    }
+
+    fn sleep(&mut self, cpu: &hal::pac::CPU, eeprom: &mut hal::Eeprom) {
+        power::sleep_enable(cpu, power::SleepMode::PowerDown);
+
+        // Watchdog has already been started, so just sleep
+        sleep_until_expired(cpu);
+
+        // Sleep for the rest of the periods
+        for i in 1..self.schedule.timeouts().len() {
+            // This also re-enables the interrupt
+            self.watchdog
+                .configure(wdt_config(self.schedule.timeouts()[i]));
+
+            sleep_until_expired(cpu);
+        }
+        // Restart watchdog immediately after waking to minimize lost cycles
+        self.start_watchdog(cpu, eeprom);
+
+        power::sleep_disable(cpu);
+    }
+}
+
+/// Sleep using Timer2 clocked asynchronously from a 32.768 kHz crystal on
+/// TOSC1/TOSC2. The timer runs continuously in CTC mode, so time spent awake
+/// does not affect the period.
+#[cfg(feature = "crystal")]
+pub struct Crystal {
+    tc2: hal::pac::TC2,
+    /// Compare intervals in each period
+    intervals: u8,
+    /// Length of each interval in ticks. The period is rarely a multiple of a
+    /// single interval, so the first `long_intervals` are a tick longer.
+    interval_ticks: u16,
+    long_intervals: u8,
+}
+
+#[cfg(feature = "crystal")]
+impl Crystal {
+    const CRYSTAL_FREQUENCY_HZ: u32 = 32_768;
+    const PRESCALER: u32 = 1024;
+    const TICKS_PER_S: u32 = Self::CRYSTAL_FREQUENCY_HZ / Self::PRESCALER;
+    /// Longest interval of the 8-bit counter
+    const MAX_INTERVAL_TICKS: u32 = 256;
+
+    /// Wake up every `period_ms`, rounded to the nearest tick (31.25 ms)
+    pub fn new(tc2: hal::pac::TC2, period_ms: u32) -> Self {
+        let ticks = ((period_ms * Self::TICKS_PER_S + 500) / 1000).max(1);
+        let intervals = (ticks + Self::MAX_INTERVAL_TICKS - 1) / Self::MAX_INTERVAL_TICKS;
+        Self {
+            tc2,
+            intervals: intervals as u8,
+            interval_ticks: (ticks / intervals) as u16,
+            long_intervals: (ticks % intervals) as u8,
+        }
+    }
+
+    /// Set the length of interval `i` of the period, which must be done
+    /// before the counter reaches the end of it
+    fn set_interval(&mut self, i: u8) {
+        let ticks = self.interval_ticks + u16::from(i < self.long_intervals);
+        self.tc2
+            .ocr2a
+            .write(|w| unsafe { w.bits((ticks - 1) as u8) });
+    }
+
+    /// Wait until writes to the asynchronous timer registers have taken effect
+    fn sync(&self) {
+        while self.tc2.assr.read().bits() & 0b1_1111 != 0 {}
+    }
+}
+
+// The default period is a whole number of ticks, so it doesn't drift
+#[cfg(feature = "crystal")]
+const _: () = assert!(sleep::PERIOD_MS * Crystal::TICKS_PER_S % 1000 == 0);
+
+#[cfg(feature = "crystal")]
+impl SleepTimer for Crystal {
+    fn start(&mut self, cpu: &hal::pac::CPU, _eeprom: &mut hal::Eeprom) {
+        cpu.prr.modify(|_, w| w.prtim2().clear_bit());
+
+        // Interrupts must be disabled while switching to the asynchronous
+        // clock, since the registers may be corrupted
+        self.tc2.timsk2.reset();
+        self.tc2.assr.write(|w| w.as2().set_bit());
+        self.tc2.tcnt2.write(|w| unsafe { w.bits(0) });
+        self.set_interval(0);
+        self.tc2.tccr2a.write(|w| w.wgm2().ctc());
+        self.tc2.tccr2b.write(|w| w.cs2().prescale_1024());
+        self.sync();
+
+        self.tc2.tifr2.write(|w| w.ocf2a().set_bit());
+        self.tc2.timsk2.write(|w| w.ocie2a().set_bit());
+    }
+
+    fn plan(&mut self, _conditions: Option<Conditions>, _eeprom: &mut hal::Eeprom) {}
+
+    fn sleep(&mut self, cpu: &hal::pac::CPU, _eeprom: &mut hal::Eeprom) {
+        power::sleep_enable(cpu, power::SleepMode::PowerSave);
+
+        // The current period started at the last compare match, before waking
+        // up, so every interval of the period is still to come
+        for i in 0..self.intervals {
+            // After waking, at least one TOSC cycle must pass before sleeping
+            // again, otherwise the interrupt logic won't be ready. Waiting for
+            // the compare register written at the start of the interval to
+            // update guarantees this.
+            self.sync();
+
+            sleep_until_expired(cpu);
+
+            self.set_interval((i + 1) % self.intervals);
+        }
+
+        power::sleep_disable(cpu);
+    }
+}
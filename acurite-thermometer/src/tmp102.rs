@@ -1,44 +1,59 @@
-use bitflags::bitflags;
+use acurite_protocol::DeciCelsius;
+use acurite_thermometer_core::config::Config as DeviceConfig;
+use acurite_thermometer_core::poll::{poll, PollError};
+use acurite_thermometer_core::tmp102::{Config, Limit, Mode, Reading};
 use embedded_hal::blocking::{
     delay::DelayMs,
     i2c::{Write, WriteRead},
 };
 
-const ADDR: u8 = 0x48;
-
-bitflags! {
-    #[repr(transparent)]
-    pub struct Config: u16 {
-        const OS = 1 << 15;
-        const R1 = 1 << 14;
-        const R2 = 1 << 13;
-        const F1 = 1 << 12;
-        const F2 = 1 << 11;
-        const POL = 1 << 10;
-        const TM = 1 << 9;
-        const SD = 1 << 8;
-        const CR1 = 1 << 7;
-        const CR0 = 1 << 6;
-        const AL = 1 << 5;
-        const EM = 1 << 4;
-    }
-}
-
-impl Config {
-    const fn from_bytes(bytes: [u8; 2]) -> Self {
-        Self::from_bits_retain(u16::from_be_bytes(bytes))
-    }
-}
+use super::sensor::{Address, TemperatureSensor};
 
 #[repr(u8)]
 enum Register {
     Temperature = 0x00,
     Config = 0x01,
+    TLow = 0x02,
+    THigh = 0x03,
+}
+
+impl Register {
+    const fn limit(limit: Limit) -> Self {
+        match limit {
+            Limit::Low => Self::TLow,
+            Limit::High => Self::THigh,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tmp102Error<E> {
+    I2c(E),
+    /// The conversion didn't finish after the maximum number of polls
+    Timeout,
+    /// The reading is outside the range the sensor can measure, or wasn't
+    /// converted in the configured mode
+    Implausible(Reading),
+}
+
+impl<E> From<PollError<E>> for Tmp102Error<E> {
+    fn from(error: PollError<E>) -> Self {
+        match error {
+            PollError::Check(e) => Self::I2c(e),
+            PollError::Timeout => Self::Timeout,
+        }
+    }
 }
 
+/// Default number of times to poll for the end of a one-shot conversion, 2 ms
+/// apart. This allows for the maximum conversion time of 35 ms.
+pub const DEFAULT_MAX_POLLS: u8 = 10;
+
 pub struct Tmp102<I, D> {
     i2c: I,
     delay: D,
+    address: Address,
+    max_polls: u8,
 }
 
 impl<I, D, E> Tmp102<I, D>
@@ -46,41 +61,152 @@ where
     I: Write<Error = E> + WriteRead<Error = E>,
     D: DelayMs<u8>,
 {
-    pub fn new(i2c: I, delay: D) -> Self {
-        Self { i2c, delay }
+    /// Create a driver that polls up to `max_polls` times, 2 ms apart, for
+    /// the end of a one-shot conversion before giving up
+    pub fn new(i2c: I, delay: D, address: Address, max_polls: u8) -> Self {
+        Self {
+            i2c,
+            delay,
+            address,
+            max_polls,
+        }
     }
 
-    fn register_read<const N: usize>(
-        &mut self,
-        reg: Register,
-    ) -> Result<[u8; N], <I as WriteRead>::Error> {
-        let mut value = [0u8; N];
-        self.i2c.write_read(ADDR, &[reg as u8], &mut value)?;
+    fn register_read(&mut self, reg: Register) -> Result<[u8; 2], <I as WriteRead>::Error> {
+        let mut value = [0u8; 2];
+        self.i2c
+            .write_read(self.address as u8, &[reg as u8], &mut value)?;
         Ok(value)
     }
 
-    fn register_write<const N: usize>(
-        &mut self,
-        reg: Register,
-        value: [u8; N],
-    ) -> Result<(), <I as Write>::Error> {
-        self.i2c.write(ADDR, &[reg as u8, value[0], value[1]])
+    fn register_write(&mut self, reg: Register, value: [u8; 2]) -> Result<(), <I as Write>::Error> {
+        self.i2c
+            .write(self.address as u8, &[reg as u8, value[0], value[1]])
+    }
+
+    pub fn read_config(&mut self) -> Result<Config, E> {
+        Ok(Config::from_bytes(self.register_read(Register::Config)?))
+    }
+
+    /// Write the configuration. The read-only AL bit is ignored.
+    pub fn write_config(&mut self, config: Config) -> Result<(), E> {
+        self.register_write(Register::Config, config.bits().to_be_bytes())
+    }
+
+    /// Read the result of the last conversion, either one-shot or continuous
+    pub fn read(&mut self) -> Result<Reading, E> {
+        Ok(Reading::from_register(i16::from_be_bytes(
+            self.register_read(Register::Temperature)?,
+        )))
     }
 
-    pub fn oneshot(&mut self, config: Config) -> Result<i16, E> {
-        let config = config | Config::SD | Config::OS;
-        self.register_write(Register::Config, config.bits().to_be_bytes())?;
+    /// Take a single measurement and shut down again. The reading is checked
+    /// against the range of the sensor and the mode set in `config`.
+    pub fn oneshot(&mut self, config: Config) -> Result<Reading, Tmp102Error<E>> {
+        self.write_config(config | Config::SD | Config::OS)
+            .map_err(Tmp102Error::I2c)?;
         // A single conversion typically takes 26 ms
         self.delay.delay_ms(30);
-        loop {
-            let config = Config::from_bytes(self.register_read(Register::Config)?);
-            if config.contains(Config::OS) {
-                break;
-            }
-            self.delay.delay_ms(2);
+        poll(
+            self,
+            self.max_polls,
+            |s| Ok(s.read_config()?.contains(Config::OS)),
+            |s| s.delay.delay_ms(2),
+        )?;
+
+        let reading = self.read().map_err(Tmp102Error::I2c)?;
+        if reading.mode() != config.mode() || !reading.is_plausible() {
+            return Err(Tmp102Error::Implausible(reading));
         }
+        Ok(reading)
+    }
+}
 
-        let temp_reg = i16::from_be_bytes(self.register_read(Register::Temperature)?);
-        Ok(temp_reg)
+// The thermometer only uses one-shot conversions, the rest is for variants
+// such as a freezer alarm that wakes on the ALERT pin
+#[allow(dead_code)]
+impl<I, D, E> Tmp102<I, D>
+where
+    I: Write<Error = E> + WriteRead<Error = E>,
+    D: DelayMs<u8>,
+{
+    /// Read an alert limit. The limit registers have no mode flag, so the mode
+    /// set in the configuration must be given.
+    pub fn read_limit(&mut self, limit: Limit, mode: Mode) -> Result<Reading, E> {
+        Ok(Reading::from_limit_register(
+            i16::from_be_bytes(self.register_read(Register::limit(limit))?),
+            mode,
+        ))
+    }
+
+    /// Write an alert limit, which must be in the mode set in the
+    /// configuration. See [`Reading::from_temperature`].
+    pub fn write_limit(&mut self, limit: Limit, value: Reading) -> Result<(), E> {
+        self.register_write(
+            Register::limit(limit),
+            value.to_limit_register().to_be_bytes(),
+        )
+    }
+
+    /// Whether the alert is active. In interrupt mode, this also clears it.
+    pub fn alert(&mut self) -> Result<bool, E> {
+        Ok(self.read_config()?.alert())
+    }
+
+    /// Start converting continuously at the rate set in `config`. The latest
+    /// conversion can then be read with [`Self::read`] at any time.
+    pub fn start_continuous(&mut self, config: Config) -> Result<(), E> {
+        self.write_config(config.difference(Config::SD | Config::OS))
+    }
+
+    /// Stop converting continuously, to save power
+    pub fn shutdown(&mut self, config: Config) -> Result<(), E> {
+        self.write_config(config.difference(Config::OS) | Config::SD)
+    }
+}
+
+impl<I, D, E> TemperatureSensor for Tmp102<I, D>
+where
+    I: Write<Error = E> + WriteRead<Error = E>,
+    D: DelayMs<u8>,
+{
+    type Error = Tmp102Error<E>;
+
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    fn set_address(&mut self, address: Address) {
+        self.address = address;
+    }
+
+    fn probe(&mut self, config: &DeviceConfig) -> bool {
+        // This also discards the first reading, which seems to be bad? Only
+        // happens on the real board.
+        !matches!(
+            self.oneshot(Config::from_bits_retain(config.sensor_config)),
+            Err(Tmp102Error::I2c(_))
+        )
+    }
+
+    fn measure(&mut self, config: &DeviceConfig) -> Result<DeciCelsius, Self::Error> {
+        self.oneshot(Config::from_bits_retain(config.sensor_config))
+            .map(Reading::temperature)
+    }
+
+    fn error_blinks(error: &Self::Error) -> u8 {
+        match error {
+            Tmp102Error::I2c(_) => 2,
+            Tmp102Error::Timeout => 3,
+            Tmp102Error::Implausible(_) => 5,
+        }
+    }
+
+    fn error_message(error: &Self::Error) -> &'static str {
+        match error {
+            Tmp102Error::I2c(_) => "failed to read temperature",
+            Tmp102Error::Timeout => "temperature conversion timed out",
+            Tmp102Error::Implausible(_) => "implausible temperature reading",
+        }
     }
 }
@@ -0,0 +1,250 @@
+use acurite_protocol::DeciCelsius;
+use acurite_thermometer_core::config::Config as DeviceConfig;
+use acurite_thermometer_core::poll::{poll, PollError};
+use acurite_thermometer_core::tmp117::{self, Averaging, Config, ConversionMode};
+use embedded_hal::blocking::{
+    delay::DelayMs,
+    i2c::{Write, WriteRead},
+};
+
+use super::sensor::{Address, TemperatureSensor};
+
+#[repr(u8)]
+enum Register {
+    Temperature = 0x00,
+    Config = 0x01,
+    EepromUnlock = 0x04,
+    TemperatureOffset = 0x07,
+    DeviceId = 0x0f,
+}
+
+/// Bits of the EEPROM unlock register
+const EEPROM_UNLOCK: u16 = 1 << 15;
+const EEPROM_UNLOCK_BUSY: u16 = 1 << 14;
+
+/// Programming the EEPROM takes 7 ms
+const EEPROM_MAX_POLLS: u8 = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tmp117Error<E> {
+    I2c(E),
+    /// The conversion or EEPROM write didn't finish after the maximum number
+    /// of polls
+    Timeout,
+    /// The reading is outside the range the sensor can measure
+    Implausible(i16),
+    /// A different device answered, with this device ID
+    WrongDevice(u16),
+}
+
+impl<E> From<PollError<E>> for Tmp117Error<E> {
+    fn from(error: PollError<E>) -> Self {
+        match error {
+            PollError::Check(e) => Self::I2c(e),
+            PollError::Timeout => Self::Timeout,
+        }
+    }
+}
+
+pub const DEFAULT_AVERAGING: Averaging = Averaging::X8;
+
+/// Default number of times to poll for the end of a one-shot conversion, 2 ms
+/// apart, after waiting for the typical conversion time
+pub const DEFAULT_MAX_POLLS: u8 = 10;
+
+pub struct Tmp117<I, D> {
+    i2c: I,
+    delay: D,
+    address: Address,
+    averaging: Averaging,
+    max_polls: u8,
+}
+
+impl<I, D, E> Tmp117<I, D>
+where
+    I: Write<Error = E> + WriteRead<Error = E>,
+    D: DelayMs<u16>,
+{
+    /// Create a driver that polls up to `max_polls` times, 2 ms apart, for
+    /// the end of a one-shot conversion before giving up
+    pub fn new(i2c: I, delay: D, address: Address, max_polls: u8) -> Self {
+        Self {
+            i2c,
+            delay,
+            address,
+            averaging: DEFAULT_AVERAGING,
+            max_polls,
+        }
+    }
+
+    /// Set the number of conversions averaged by [`Self::oneshot`]. More
+    /// averaging reduces noise, but keeps the sensor awake for longer.
+    pub fn set_averaging(&mut self, averaging: Averaging) {
+        self.averaging = averaging;
+    }
+
+    fn register_read(&mut self, reg: Register) -> Result<[u8; 2], <I as WriteRead>::Error> {
+        let mut value = [0u8; 2];
+        self.i2c
+            .write_read(self.address as u8, &[reg as u8], &mut value)?;
+        Ok(value)
+    }
+
+    fn register_write(&mut self, reg: Register, value: [u8; 2]) -> Result<(), <I as Write>::Error> {
+        self.i2c
+            .write(self.address as u8, &[reg as u8, value[0], value[1]])
+    }
+
+    /// Read the configuration. This clears the data ready flag.
+    pub fn read_config(&mut self) -> Result<Config, E> {
+        Ok(Config::from_bytes(self.register_read(Register::Config)?))
+    }
+
+    /// Write the configuration. The read-only flags are ignored.
+    pub fn write_config(&mut self, config: Config) -> Result<(), E> {
+        self.register_write(Register::Config, config.bits().to_be_bytes())
+    }
+
+    /// Device ID, without the revision
+    pub fn device_id(&mut self) -> Result<u16, E> {
+        Ok(u16::from_be_bytes(self.register_read(Register::DeviceId)?) & 0x0fff)
+    }
+
+    /// Check that the device at the address is a TMP117
+    pub fn verify(&mut self) -> Result<(), Tmp117Error<E>> {
+        match self.device_id().map_err(Tmp117Error::I2c)? {
+            tmp117::DEVICE_ID => Ok(()),
+            id => Err(Tmp117Error::WrongDevice(id)),
+        }
+    }
+
+    /// Read the result of the last conversion
+    pub fn read(&mut self) -> Result<i16, E> {
+        Ok(i16::from_be_bytes(
+            self.register_read(Register::Temperature)?,
+        ))
+    }
+
+    /// Take a single averaged measurement and shut down again. The reading is
+    /// checked against the range of the sensor.
+    pub fn oneshot(&mut self) -> Result<i16, Tmp117Error<E>> {
+        let config = Config::empty()
+            .conversion_mode(ConversionMode::OneShot)
+            .averaging(self.averaging);
+        self.write_config(config).map_err(Tmp117Error::I2c)?;
+        self.delay.delay_ms(self.averaging.conversion_time_ms());
+        poll(
+            self,
+            self.max_polls,
+            |s| Ok(s.read_config()?.contains(Config::DATA_READY)),
+            |s| s.delay.delay_ms(2),
+        )?;
+
+        let reading = self.read().map_err(Tmp117Error::I2c)?;
+        if !tmp117::SPECIFIED_RANGE.contains(&reading) {
+            return Err(Tmp117Error::Implausible(reading));
+        }
+        Ok(reading)
+    }
+
+    /// Read the offset added to each conversion, in the same format as the
+    /// temperature register
+    pub fn read_offset(&mut self) -> Result<i16, E> {
+        Ok(i16::from_be_bytes(
+            self.register_read(Register::TemperatureOffset)?,
+        ))
+    }
+
+    /// Set the offset added to each conversion until the next reset
+    pub fn write_offset(&mut self, offset: i16) -> Result<(), E> {
+        self.register_write(Register::TemperatureOffset, offset.to_be_bytes())
+    }
+
+    /// Set the offset added to each conversion and program it into the sensor's
+    /// EEPROM, so it is loaded again on reset
+    pub fn store_offset(&mut self, offset: i16) -> Result<(), Tmp117Error<E>> {
+        self.register_write(Register::EepromUnlock, EEPROM_UNLOCK.to_be_bytes())
+            .map_err(Tmp117Error::I2c)?;
+        // Writing a register while the EEPROM is unlocked also programs it
+        let result = self
+            .write_offset(offset)
+            .map_err(Tmp117Error::I2c)
+            .and_then(|()| {
+                self.delay.delay_ms(2);
+                poll(
+                    self,
+                    EEPROM_MAX_POLLS,
+                    |s| {
+                        let unlock = u16::from_be_bytes(s.register_read(Register::EepromUnlock)?);
+                        Ok(unlock & EEPROM_UNLOCK_BUSY == 0)
+                    },
+                    |s| s.delay.delay_ms(2),
+                )
+                .map_err(Tmp117Error::from)
+            });
+        // Lock again even if programming failed, so that later writes don't
+        // wear out the EEPROM
+        self.register_write(Register::EepromUnlock, [0, 0])
+            .map_err(Tmp117Error::I2c)?;
+        result
+    }
+
+    /// Program `offset` into the sensor's EEPROM, unless it is already stored,
+    /// so that the EEPROM isn't worn out by programming it on every boot
+    pub fn sync_offset(&mut self, offset: DeciCelsius) -> Result<(), Tmp117Error<E>> {
+        // The configured offset is limited to a few degrees, so it always fits
+        let register = tmp117::offset_register(offset).unwrap_or(0);
+        if self.read_offset().map_err(Tmp117Error::I2c)? != register {
+            self.store_offset(register)?;
+        }
+        Ok(())
+    }
+}
+
+impl<I, D, E> TemperatureSensor for Tmp117<I, D>
+where
+    I: Write<Error = E> + WriteRead<Error = E>,
+    D: DelayMs<u16>,
+{
+    type Error = Tmp117Error<E>;
+
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    fn set_address(&mut self, address: Address) {
+        self.address = address;
+    }
+
+    const STORES_OFFSET: bool = true;
+
+    /// Also programs the configured offset into the sensor. A sensor that
+    /// can't be programmed isn't used, since its readings would be off.
+    fn probe(&mut self, config: &DeviceConfig) -> bool {
+        self.verify().is_ok() && self.sync_offset(config.temperature_offset).is_ok()
+    }
+
+    /// The TMP102 configuration is ignored, averaging is set with
+    /// [`Tmp117::set_averaging`] instead
+    fn measure(&mut self, _config: &DeviceConfig) -> Result<DeciCelsius, Self::Error> {
+        self.oneshot().map(tmp117::temperature)
+    }
+
+    fn error_blinks(error: &Self::Error) -> u8 {
+        match error {
+            Tmp117Error::I2c(_) => 2,
+            Tmp117Error::Timeout => 3,
+            Tmp117Error::Implausible(_) => 5,
+            Tmp117Error::WrongDevice(_) => 6,
+        }
+    }
+
+    fn error_message(error: &Self::Error) -> &'static str {
+        match error {
+            Tmp117Error::I2c(_) => "failed to read temperature",
+            Tmp117Error::Timeout => "temperature conversion timed out",
+            Tmp117Error::Implausible(_) => "implausible temperature reading",
+            Tmp117Error::WrongDevice(_) => "sensor is not a TMP117",
+        }
+    }
+}
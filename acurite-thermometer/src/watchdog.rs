@@ -1,3 +1,4 @@
+use acurite_thermometer_core::sleep::Timeout;
 use hal::wdt::WdtOps;
 
 use super::hal;
@@ -15,21 +16,9 @@ impl Config {
         self
     }
 
-    pub const fn timeout(mut self, timeout: hal::wdt::Timeout) -> Self {
+    pub const fn timeout(mut self, timeout: Timeout) -> Self {
         self.0 &= !(0b00010111);
-        use hal::wdt::Timeout::*;
-        let wdp = match timeout {
-            Ms16 => 0,
-            Ms32 => 1,
-            Ms64 => 2,
-            Ms125 => 3,
-            Ms250 => 4,
-            Ms500 => 5,
-            Ms1000 => 6,
-            Ms2000 => 7,
-            Ms4000 => 8,
-            Ms8000 => 9,
-        };
+        let wdp = timeout.prescaler();
         self.0 |= (wdp & 0b1000) << 2;
         self.0 |= wdp & 0b0111;
         self
@@ -57,4 +46,18 @@ impl Watchdog {
         // Apply config
         self.wdt.wdtcr.write(|w| unsafe { w.bits(config.0) });
     }
+
+    /// Restart the current timeout
+    pub fn reset(&mut self) {
+        avr_device::asm::wdr();
+    }
+
+    /// Whether a timeout has expired without the interrupt being handled
+    pub fn interrupt_pending(&self) -> bool {
+        self.wdt.wdtcr.read().wdif().bit_is_set()
+    }
+
+    pub fn clear_interrupt(&mut self) {
+        self.wdt.wdtcr.modify(|_, w| w.wdif().set_bit());
+    }
 }